@@ -0,0 +1,490 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// The list of files in content/assets.toml. Nothing here touches the GPU: the
+// front end walks the manifest and hands each file to frenderer, keeping the
+// results in a `Registry` per kind and a `Cache` per file type.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default)]
+    pub models: BTreeMap<String, ModelEntry>,
+    pub room: RoomEntry,
+    #[serde(default)]
+    pub characters: BTreeMap<String, CharacterEntry>,
+    #[serde(default)]
+    pub animations: BTreeMap<String, AnimationEntry>,
+    #[serde(default)]
+    pub sounds: BTreeMap<String, String>,
+    pub font: Option<FontEntry>,
+    #[serde(skip)]
+    pub root: PathBuf, //the folder the manifest is in
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelEntry {
+    pub mesh: String,
+    pub texture: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoomEntry {
+    pub mesh: String,
+    pub themes: String, //folder searched for themes at startup
+}
+
+// How one room looks: the room mesh's texture, which holds the floor, the
+// wallpaper and the trim in one image, and optionally its own door texture.
+// `Room::tex_idx` picks one of these.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+    pub room: PathBuf,
+    pub door: Option<PathBuf>, //None keeps the door model's own texture
+}
+
+//how many themes content/ ships with, for runs that paint rooms without loading them
+pub const SHIPPED_THEMES: usize = 8;
+
+// Every folder in `dir` holding a room.png is a theme, and so is every png
+// lying in `dir` itself. Sorted by name so a seed always paints the same rooms.
+pub fn discover_themes(dir: &Path) -> Result<Vec<Theme>, AssetError> {
+    let entries = std::fs::read_dir(dir).map_err(|err| AssetError::Io(dir.to_path_buf(), err))?;
+    let mut themes = vec![];
+    let mut problems = vec![];
+    for entry in entries {
+        let path = entry
+            .map_err(|err| AssetError::Io(dir.to_path_buf(), err))?
+            .path();
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        if path.is_dir() {
+            let room = path.join("room.png");
+            if !room.is_file() {
+                problems.push(format!("theme {} has no room.png", path.display()));
+                continue;
+            }
+            let door = path.join("door.png");
+            themes.push(Theme {
+                name,
+                room,
+                door: if door.is_file() { Some(door) } else { None },
+            });
+        } else if path.extension().is_some_and(|ext| ext == "png") {
+            themes.push(Theme {
+                name,
+                room: path,
+                door: None,
+            });
+        }
+    }
+    if themes.is_empty() && problems.is_empty() {
+        problems.push(format!("no themes in {}", dir.display()));
+    }
+    if !problems.is_empty() {
+        return Err(AssetError::Problems(problems));
+    }
+    themes.sort_by(|a, b| a.name.cmp(&b.name));
+    return Ok(themes);
+}
+
+//one image per character, drawn on mesh for text the game writes at runtime
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FontEntry {
+    pub mesh: String,
+    pub glyphs: String, //folder of pngs named by the character's hex code, 41.png is A
+}
+
+// Every png in `dir` whose name is a hex character code. Anything else in the
+// folder is a mistake worth hearing about, not something to skip quietly.
+pub fn discover_glyphs(dir: &Path) -> Result<BTreeMap<char, PathBuf>, AssetError> {
+    let entries = std::fs::read_dir(dir).map_err(|err| AssetError::Io(dir.to_path_buf(), err))?;
+    let mut glyphs = BTreeMap::new();
+    let mut problems = vec![];
+    for entry in entries {
+        let path = entry
+            .map_err(|err| AssetError::Io(dir.to_path_buf(), err))?
+            .path();
+        let ch = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| u32::from_str_radix(stem, 16).ok())
+            .and_then(char::from_u32);
+        match ch {
+            Some(ch) if path.extension().is_some_and(|ext| ext == "png") => {
+                glyphs.insert(ch, path);
+            }
+            _ => problems.push(format!("{} is not a glyph like 41.png", path.display())),
+        }
+    }
+    if glyphs.is_empty() && problems.is_empty() {
+        problems.push(format!("no glyphs in {}", dir.display()));
+    }
+    if !problems.is_empty() {
+        return Err(AssetError::Problems(problems));
+    }
+    return Ok(glyphs);
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CharacterEntry {
+    pub mesh: String,
+    pub nodes: Vec<String>,
+    pub texture: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnimationEntry {
+    pub file: String,
+    pub character: String,
+    pub clip: String,
+    #[serde(default)]
+    pub looping: bool,
+}
+
+impl Manifest {
+    pub fn parse(text: &str, root: &Path) -> Result<Manifest, AssetError> {
+        let mut manifest: Manifest =
+            toml::from_str(text).map_err(|err| AssetError::Parse(err.to_string()))?;
+        manifest.root = root.to_path_buf();
+        return Ok(manifest);
+    }
+
+    //parses the manifest and checks it before anything is loaded
+    pub fn load(path: &Path) -> Result<Manifest, AssetError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| AssetError::Io(path.to_path_buf(), err))?;
+        let root = path.parent().unwrap_or_else(|| Path::new(""));
+        let manifest = Manifest::parse(&text, root)?;
+        manifest.check()?;
+        return Ok(manifest);
+    }
+
+    pub fn path(&self, file: &str) -> PathBuf {
+        return self.root.join(file);
+    }
+
+    //every file the manifest mentions, each once
+    pub fn files(&self) -> BTreeSet<&str> {
+        let mut files = BTreeSet::new();
+        for model in self.models.values() {
+            files.insert(model.mesh.as_str());
+            files.insert(model.texture.as_str());
+        }
+        files.insert(self.room.mesh.as_str());
+        for character in self.characters.values() {
+            files.insert(character.mesh.as_str());
+            files.insert(character.texture.as_str());
+        }
+        files.extend(self.animations.values().map(|anim| anim.file.as_str()));
+        files.extend(self.sounds.values().map(String::as_str));
+        if let Some(font) = &self.font {
+            files.insert(font.mesh.as_str());
+        }
+        return files;
+    }
+
+    pub fn model(&self, name: &str) -> Result<&ModelEntry, AssetError> {
+        return self.models.get(name).ok_or_else(|| AssetError::Unknown {
+            kind: "model",
+            name: name.to_string(),
+        });
+    }
+
+    pub fn themes(&self) -> Result<Vec<Theme>, AssetError> {
+        return discover_themes(&self.path(&self.room.themes));
+    }
+
+    //no font in the manifest is no glyphs, and the game draws no text
+    pub fn glyphs(&self) -> Result<BTreeMap<char, PathBuf>, AssetError> {
+        return match &self.font {
+            Some(font) => discover_glyphs(&self.path(&font.glyphs)),
+            None => Ok(BTreeMap::new()),
+        };
+    }
+
+    // Lists every missing file and dangling name at once, so one run of the
+    // game tells you everything that needs fixing.
+    pub fn check(&self) -> Result<(), AssetError> {
+        let mut problems = vec![];
+        for file in self.files() {
+            if !self.path(file).is_file() {
+                problems.push(format!("missing file {}", self.path(file).display()));
+            }
+        }
+        match self.themes() {
+            Ok(_) => {}
+            Err(AssetError::Problems(found)) => problems.extend(found),
+            Err(err) => problems.push(err.to_string()),
+        }
+        match self.glyphs() {
+            Ok(_) => {}
+            Err(AssetError::Problems(found)) => problems.extend(found),
+            Err(err) => problems.push(err.to_string()),
+        }
+        for (name, anim) in self.animations.iter() {
+            if !self.characters.contains_key(&anim.character) {
+                problems.push(format!(
+                    "animation {} is for unknown character {}",
+                    name, anim.character
+                ));
+            }
+        }
+        if !problems.is_empty() {
+            return Err(AssetError::Problems(problems));
+        }
+        return Ok(());
+    }
+}
+
+//loaded things by the name the manifest gave them
+pub struct Registry<H> {
+    kind: &'static str,
+    items: HashMap<String, H>,
+}
+
+impl<H: Clone> Registry<H> {
+    pub fn new(kind: &'static str) -> Self {
+        return Registry {
+            kind,
+            items: HashMap::new(),
+        };
+    }
+
+    pub fn insert(&mut self, name: &str, handle: H) {
+        self.items.insert(name.to_string(), handle);
+    }
+
+    pub fn get(&self, name: &str) -> Result<H, AssetError> {
+        return self
+            .items
+            .get(name)
+            .cloned()
+            .ok_or_else(|| AssetError::Unknown {
+                kind: self.kind,
+                name: name.to_string(),
+            });
+    }
+
+    pub fn len(&self) -> usize {
+        return self.items.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.items.is_empty();
+    }
+}
+
+//hands back the first load of a path every time it is asked for again
+pub struct Cache<H> {
+    loaded: HashMap<PathBuf, H>,
+}
+
+impl<H: Clone> Cache<H> {
+    pub fn new() -> Self {
+        return Cache {
+            loaded: HashMap::new(),
+        };
+    }
+
+    pub fn load<E>(
+        &mut self,
+        path: &Path,
+        load: impl FnOnce(&Path) -> Result<H, E>,
+    ) -> Result<H, E> {
+        if let Some(handle) = self.loaded.get(path) {
+            return Ok(handle.clone());
+        }
+        let handle = load(path)?;
+        self.loaded.insert(path.to_path_buf(), handle.clone());
+        return Ok(handle);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.loaded.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.loaded.is_empty();
+    }
+}
+
+impl<H: Clone> Default for Cache<H> {
+    fn default() -> Self {
+        return Cache::new();
+    }
+}
+
+#[derive(Debug)]
+pub enum AssetError {
+    Io(PathBuf, std::io::Error),
+    Parse(String),
+    Problems(Vec<String>),
+    Unknown { kind: &'static str, name: String },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            AssetError::Parse(message) => write!(f, "bad asset manifest: {}", message),
+            AssetError::Problems(problems) => {
+                write!(f, "asset manifest has {} problem(s):", problems.len())?;
+                for problem in problems.iter() {
+                    write!(f, "\n  {}", problem)?;
+                }
+                return Ok(());
+            }
+            AssetError::Unknown { kind, name } => {
+                write!(f, "no {} named {} in the asset manifest", kind, name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity;
+
+    fn content() -> PathBuf {
+        return Path::new(env!("CARGO_MANIFEST_DIR")).join("content");
+    }
+
+    #[test]
+    fn the_shipped_manifest_is_complete() {
+        let manifest = Manifest::load(&content().join("assets.toml")).unwrap();
+        assert_eq!(manifest.themes().unwrap().len(), SHIPPED_THEMES);
+        assert!(manifest.models.contains_key("door"));
+        assert!(manifest.models.contains_key("door_locked"));
+        assert!(manifest.models.contains_key("stairs"));
+        //menus, the transition curtain, the minimap, door marks, floors,
+        //partitions and ghosts share one mesh
+        let planes = manifest
+            .models
+            .values()
+            .filter(|model| model.mesh == "text_plane.fbx")
+            .count();
+        assert_eq!(planes, 17);
+        for colour in 0..identity::FLOOR_COLOURS.len() {
+            assert!(manifest.models.contains_key(&identity::floor_model(colour)));
+        }
+        for landmark in 0..identity::LANDMARKS.len() {
+            assert!(manifest
+                .models
+                .contains_key(&identity::landmark_model(landmark)));
+        }
+        let glyphs = manifest.glyphs().unwrap();
+        for ch in "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ:./-".chars() {
+            assert!(glyphs.contains_key(&ch), "no glyph for {}", ch);
+        }
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_once() {
+        let text = r#"
+            [models.door]
+            mesh = "nope.fbx"
+            texture = "floor.png"
+            [room]
+            mesh = "room.fbx"
+            themes = "gone"
+            [animations.run]
+            file = "run.fbx"
+            character = "nobody"
+            clip = "Root|Run"
+        "#;
+        let manifest = Manifest::parse(text, &content()).unwrap();
+        match manifest.check() {
+            Err(AssetError::Problems(problems)) => {
+                assert_eq!(problems.len(), 3, "{:?}", problems);
+                assert!(problems.iter().any(|p| p.contains("gone")));
+                assert!(problems.iter().any(|p| p.contains("nope.fbx")));
+                assert!(problems.iter().any(|p| p.contains("nobody")));
+            }
+            other => panic!("expected problems, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn typos_in_the_manifest_are_errors() {
+        let err = Manifest::parse("[room]\nmesh = \"a\"\ntheme = \"b\"\n", &content()).unwrap_err();
+        assert!(err.to_string().contains("theme"), "{}", err);
+    }
+
+    #[test]
+    fn themes_are_folders_or_loose_pngs() {
+        let dir = std::env::temp_dir().join(format!("scene3d-themes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("study")).unwrap();
+        std::fs::write(dir.join("study/room.png"), b"").unwrap();
+        std::fs::write(dir.join("study/door.png"), b"").unwrap();
+        std::fs::write(dir.join("attic.png"), b"").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"").unwrap();
+        let themes = discover_themes(&dir).unwrap();
+        let names: Vec<&str> = themes.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["attic", "study"]);
+        assert_eq!(themes[0].door, None);
+        assert_eq!(themes[1].door, Some(dir.join("study/door.png")));
+
+        std::fs::create_dir_all(dir.join("empty")).unwrap();
+        let err = discover_themes(&dir).unwrap_err();
+        assert!(err.to_string().contains("has no room.png"), "{}", err);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn glyphs_are_named_by_character_code() {
+        let dir = std::env::temp_dir().join(format!("scene3d-glyphs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("41.png"), b"").unwrap();
+        std::fs::write(dir.join("3a.png"), b"").unwrap();
+        let glyphs = discover_glyphs(&dir).unwrap();
+        assert_eq!(glyphs.keys().collect::<String>(), ":A");
+
+        std::fs::write(dir.join("A.png.bak"), b"").unwrap();
+        let err = discover_glyphs(&dir).unwrap_err();
+        assert!(err.to_string().contains("A.png.bak"), "{}", err);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn the_cache_loads_each_path_once() {
+        let mut cache = Cache::new();
+        let mut loads = 0;
+        for file in ["a.fbx", "b.fbx", "a.fbx", "a.fbx"] {
+            let handle = cache
+                .load(Path::new(file), |path| {
+                    loads += 1;
+                    Ok::<_, ()>(path.to_path_buf())
+                })
+                .unwrap();
+            assert_eq!(handle, Path::new(file));
+        }
+        assert_eq!(loads, 2);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn unknown_names_say_what_was_asked_for() {
+        let mut models = Registry::new("model");
+        models.insert("door", 1);
+        assert_eq!(models.get("door").unwrap(), 1);
+        assert_eq!(
+            models.get("dor").unwrap_err().to_string(),
+            "no model named dor in the asset manifest"
+        );
+    }
+}
@@ -0,0 +1,242 @@
+#![allow(clippy::needless_return)]
+// Plays the game with no window and no GPU and prints one CSV row per run:
+//
+//     cargo run --release --bin headless -- --runs 1000 --rooms 7
+//
+// Runs are played by the explorer bot unless --driver random or --script says
+// otherwise. --replay FILE instead plays back one recording from the game and
+// exits nonzero if it doesn't end where it did when it was recorded. --level N
+// plays the house the [levels] curves give that level of the endless game.
+// Rooms are painted from the themes in content/ when it is there, so a seed
+// matches the game; --num-tex N paints them from N themes instead.
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use scene3d::assets::{Manifest, SHIPPED_THEMES};
+use scene3d::bot::Explorer;
+use scene3d::config::{Config, GhostRules};
+use scene3d::map::Generator;
+use scene3d::replay::Recording;
+use scene3d::sim::{self, Driver, RandomWalk, Report, Scripted};
+use scene3d::state::*;
+use scene3d::DT;
+use std::path::Path;
+use std::process::exit;
+
+struct Options {
+    runs: u64,
+    seed: u64,
+    config: Option<String>,
+    rooms: Option<usize>,
+    dead_ends: Option<usize>,
+    floors: Option<usize>,
+    ghosts: Option<usize>,
+    generator: Option<Generator>,
+    level: Option<usize>,
+    num_tex: Option<usize>,
+    max_seconds: f64,
+    driver: String,
+    script: Option<String>,
+    replay: Option<String>,
+}
+
+const USAGE: &str =
+    "usage: headless [--config FILE] [--runs N] [--seed N] [--rooms N] [--dead-ends N] \
+                     [--floors N] [--generator path|tree] [--ghosts N] [--level N] [--num-tex N] \
+                     [--max-seconds S] [--driver explorer|random] [--script FILE] [--replay FILE]";
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        runs: 100,
+        seed: 0,
+        config: None,
+        rooms: None,
+        dead_ends: None,
+        floors: None,
+        ghosts: None,
+        generator: None,
+        level: None,
+        num_tex: None,
+        max_seconds: 600.0,
+        driver: String::from("explorer"),
+        script: None,
+        replay: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(USAGE.to_string());
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value\n{}", flag, USAGE))?;
+        let bad = || format!("bad value for {}: {}", flag, value);
+        match flag.as_str() {
+            "--config" => options.config = Some(value),
+            "--runs" => options.runs = value.parse().map_err(|_| bad())?,
+            "--seed" => options.seed = value.parse().map_err(|_| bad())?,
+            "--rooms" => options.rooms = Some(value.parse().map_err(|_| bad())?),
+            "--dead-ends" => options.dead_ends = Some(value.parse().map_err(|_| bad())?),
+            "--floors" => options.floors = Some(value.parse().map_err(|_| bad())?),
+            "--generator" => options.generator = Some(value.parse()?),
+            "--ghosts" => options.ghosts = Some(value.parse().map_err(|_| bad())?),
+            "--level" => options.level = Some(value.parse().map_err(|_| bad())?),
+            "--num-tex" => options.num_tex = Some(value.parse().map_err(|_| bad())?),
+            "--max-seconds" => options.max_seconds = value.parse().map_err(|_| bad())?,
+            "--driver" => options.driver = value,
+            "--script" => options.script = Some(value),
+            "--replay" => options.replay = Some(value),
+            _ => return Err(format!("unknown option {}\n{}", flag, USAGE)),
+        }
+    }
+    if options.driver != "explorer" && options.driver != "random" {
+        return Err(format!("unknown driver {}\n{}", options.driver, USAGE));
+    }
+    if options.rooms == Some(0) {
+        return Err("--rooms must be at least 1".to_string());
+    }
+    if options.floors == Some(0) {
+        return Err("--floors must be at least 1".to_string());
+    }
+    if options.num_tex == Some(0) {
+        return Err("--num-tex must be at least 1".to_string());
+    }
+    if options.level == Some(0) {
+        return Err("--level must be at least 1".to_string());
+    }
+    let shaped = options.rooms.is_some() || options.dead_ends.is_some() || options.ghosts.is_some();
+    if options.level.is_some() && shaped {
+        return Err("--level takes its house from the [levels] section".to_string());
+    }
+    return Ok(options);
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            exit(2);
+        }
+    };
+    if let Some(path) = &options.replay {
+        let verified = Recording::load(path.as_ref()).and_then(|recording| recording.verify());
+        match verified {
+            Ok(end) => println!("{}: matched at {}", path, end),
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                exit(1);
+            }
+        }
+        return;
+    }
+    let script = options.script.as_ref().map(|path| {
+        let text = std::fs::read_to_string(path).unwrap_or_else(|err| {
+            eprintln!("{}: {}", path, err);
+            exit(1);
+        });
+        return Scripted::parse(&text).unwrap_or_else(|err| {
+            eprintln!("{}: {}", path, err);
+            exit(1);
+        });
+    });
+    let config_path = options.config.as_deref().unwrap_or("config.toml");
+    let loaded = match &options.config {
+        Some(path) => Config::load(Path::new(path)),
+        None => Config::load_or_default(Path::new(config_path)),
+    };
+    let config = loaded.unwrap_or_else(|err| {
+        eprintln!("{}: {}", config_path, err);
+        exit(1);
+    });
+    let house = config.house;
+    //only to paint rooms the way the game would for the same seed, so
+    //missing or broken art never stops a run
+    let num_tex = options.num_tex.unwrap_or_else(|| {
+        let manifest = Path::new("content/assets.toml");
+        if !manifest.is_file() {
+            return SHIPPED_THEMES;
+        }
+        return match Manifest::load(manifest).and_then(|manifest| manifest.themes()) {
+            Ok(themes) => themes.len(),
+            Err(err) => {
+                eprintln!("{}\npainting rooms in {} themes", err, SHIPPED_THEMES);
+                SHIPPED_THEMES
+            }
+        };
+    });
+    let max_ticks = (options.max_seconds / DT) as u64;
+
+    println!("seed,completed,seconds,key_seconds,rooms_visited,door_transitions,catches");
+    let mut reports: Vec<Report> = vec![];
+    for seed in options.seed..options.seed + options.runs {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let generator = options.generator.unwrap_or(house.generator);
+        let floors = options.floors.unwrap_or(house.floors);
+        let mut state = match options.level {
+            Some(level) => GameState::for_level(
+                config.levels,
+                level,
+                generator,
+                floors,
+                num_tex,
+                config.ghosts,
+                &mut rng,
+            ),
+            None => {
+                let mut state = GameState::generate_with(
+                    generator,
+                    options.rooms.unwrap_or(house.rooms),
+                    options.dead_ends.unwrap_or(house.dead_ends),
+                    floors,
+                    num_tex,
+                    &mut rng,
+                );
+                let ghosts = GhostRules {
+                    count: options.ghosts.unwrap_or(config.ghosts.count),
+                    ..config.ghosts
+                };
+                state.haunt(ghosts, &mut rng);
+                state
+            }
+        };
+        state.tuning = config.tuning;
+        let mut driver: Box<dyn Driver> = match &script {
+            Some(script) => Box::new(script.clone()),
+            None if options.driver == "random" => {
+                Box::new(RandomWalk::new(StdRng::seed_from_u64(seed)))
+            }
+            None => Box::new(Explorer::new()),
+        };
+        let report = sim::run(&mut state, &mut driver, max_ticks, &mut rng);
+        println!(
+            "{},{},{:.2},{},{},{},{}",
+            seed,
+            report.completed,
+            report.seconds(),
+            report
+                .key_tick
+                .map(|t| format!("{:.2}", t as f64 * DT))
+                .unwrap_or_default(),
+            report.rooms_visited,
+            report.door_transitions,
+            report.catches
+        );
+        reports.push(report);
+    }
+
+    let done: Vec<&Report> = reports.iter().filter(|r| r.completed).collect();
+    eprintln!("{}/{} runs finished the house", done.len(), reports.len());
+    let catches: usize = reports.iter().map(|r| r.catches).sum();
+    if catches > 0 {
+        eprintln!("ghosts caught the player {} times", catches);
+    }
+    if !done.is_empty() {
+        let n = done.len() as f64;
+        eprintln!(
+            "mean of finished runs: {:.2}s, {:.1} rooms, {:.1} doors",
+            done.iter().map(|r| r.seconds()).sum::<f64>() / n,
+            done.iter().map(|r| r.rooms_visited as f64).sum::<f64>() / n,
+            done.iter().map(|r| r.door_transitions as f64).sum::<f64>() / n
+        );
+    }
+}
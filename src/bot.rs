@@ -0,0 +1,310 @@
+use crate::geometry::*;
+use crate::rules::{Controls, CHEST_EDGE, CHEST_POS, KEY_POS};
+use crate::sim::Driver;
+use crate::state::*;
+use crate::types::*;
+use std::collections::{HashMap, VecDeque};
+use ultraviolet::Vec3;
+
+//how far around the chest the bot keeps when it is only passing by
+const CHEST_MARGIN: f32 = 0.75;
+const CHEST_CORNER: f32 = 10.0;
+
+// Plays like a first-time visitor: it remembers the doors of every room it has
+// stood in and nothing else. Until it has seen the key it heads for the
+// nearest door it has never walked through; once it has the key it walks the
+// remembered doors back to the chest.
+#[derive(Clone, Debug, Default)]
+pub struct Explorer {
+    known: HashMap<usize, Vec<(usize, Door)>>, //doors of each room we've been in
+    key_room: Option<usize>,
+}
+
+impl Explorer {
+    pub fn new() -> Self {
+        return Explorer::default();
+    }
+
+    pub fn rooms_seen(&self) -> usize {
+        return self.known.len();
+    }
+
+    fn look_around(&mut self, state: &GameState) {
+        let room = state.current_room;
+        self.known
+            .entry(room)
+            .or_insert_with(|| state.room_doors(room).collect());
+        if room == state.key_index && !state.has_key {
+            self.key_room = Some(room);
+        }
+    }
+
+    // Breadth-first over remembered doors, leaving out locked ones until the
+    // key is found. Returns the first door to take from `from` towards the
+    // nearest room that satisfies `goal`.
+    fn first_door(
+        &self,
+        from: usize,
+        has_key: bool,
+        goal: impl Fn(usize) -> bool,
+    ) -> Option<usize> {
+        let mut first: HashMap<usize, Option<usize>> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        first.insert(from, None);
+        while let Some(room) = queue.pop_front() {
+            if goal(room) {
+                return first[&room];
+            }
+            //rooms we've only seen through a doorway have no doors to follow yet
+            let doors = match self.known.get(&room) {
+                Some(doors) => doors,
+                None => continue,
+            };
+            for (dooridx, door) in doors.iter() {
+                if (!door.locked || has_key) && !first.contains_key(&door.target) {
+                    let via = first[&room].or(Some(*dooridx));
+                    first.insert(door.target, via);
+                    queue.push_back(door.target);
+                }
+            }
+        }
+        return None;
+    }
+
+    //where in the current room the bot wants to stand next
+    fn waypoint(&self, state: &GameState) -> Option<Vec3> {
+        let room = state.current_room;
+        let door = if state.has_key {
+            if room == 0 {
+                return Some(CHEST_POS);
+            }
+            self.first_door(room, true, |r| r == 0)
+        } else if self.key_room == Some(room) {
+            return Some(KEY_POS);
+        } else if let Some(key_room) = self.key_room {
+            self.first_door(room, false, |r| r == key_room)
+        } else {
+            self.first_door(room, false, |r| !self.known.contains_key(&r))
+        };
+        let shape = state.rooms[room].shape;
+        return door.map(|dooridx| door_pos(shape, state.doors[dooridx], state.tuning.room_size));
+    }
+}
+
+impl Driver for Explorer {
+    fn controls(&mut self, state: &GameState) -> Controls {
+        let mut controls = Controls::default();
+        match state.gameplaystate {
+            GameplayState::Mainscreen => controls.down = true,
+            GameplayState::Instructions => controls.play = true,
+            GameplayState::FinalScreen => {}
+            GameplayState::Play | GameplayState::TimeAttack => {
+                self.look_around(state);
+                if let Some(mut target) = self.waypoint(state) {
+                    let avoid_chest = state.current_room == 0 && target != CHEST_POS;
+                    let shape = state.rooms[state.current_room].shape;
+                    if cuts_corner(shape, state.player.pos, target) {
+                        target = elbow(shape, state.tuning.buffer);
+                    }
+                    controls = steer(state.player.pos, target, avoid_chest, state.tuning.speed);
+                }
+            }
+        }
+        return controls;
+    }
+}
+
+//the player walks in eight directions, so it stays inside the box spanned by
+//where it is and where it's going
+fn path_is_clear(from: Vec3, to: Vec3) -> bool {
+    let half = CHEST_EDGE.0.max(CHEST_EDGE.1) + CHEST_MARGIN;
+    return from.x.max(to.x) < CHEST_POS.x - half
+        || from.x.min(to.x) > CHEST_POS.x + half
+        || from.z.max(to.z) < CHEST_POS.z - half
+        || from.z.min(to.z) > CHEST_POS.z + half;
+}
+
+//whether the box the walk stays in reaches into the notch of an L
+fn cuts_corner(shape: Shape, from: Vec3, to: Vec3) -> bool {
+    return match shape.notch.map(corner_signs) {
+        Some((x, z)) => {
+            let (x, z) = (x as f32, z as f32);
+            (from.x * x).max(to.x * x) > 0.0 && (from.z * z).max(to.z * z) > 0.0
+        }
+        None => false,
+    };
+}
+
+//a spot just off the middle, away from the notch, that sees all of an L
+fn elbow(shape: Shape, margin: f32) -> Vec3 {
+    let (x, z) = shape.notch.map_or((0, 0), corner_signs);
+    return Vec3::new(-x as f32 * margin, 0.0, -z as f32 * margin);
+}
+
+// Picks the next point to walk to on the way to `target`, going around the
+// chest by its corners if it is in the way. With four corners a tiny
+// shortest-path search over {here, corners, target} is cheaper than a navmesh.
+fn detour(pos: Vec3, target: Vec3) -> Vec3 {
+    let mut points = vec![pos];
+    for (x, z) in [(1.0, 1.0), (1.0, -1.0), (-1.0, -1.0), (-1.0, 1.0)] {
+        points.push(Vec3::new(
+            CHEST_POS.x + x * CHEST_CORNER,
+            0.0,
+            CHEST_POS.z + z * CHEST_CORNER,
+        ));
+    }
+    points.push(target);
+    let goal = points.len() - 1;
+    let flat = |v: Vec3| Vec3::new(v.x, 0.0, v.z);
+
+    let mut dist = vec![f32::INFINITY; points.len()];
+    let mut prev = vec![usize::MAX; points.len()];
+    let mut done = vec![false; points.len()];
+    dist[0] = 0.0;
+    for _ in 0..points.len() {
+        let here = (0..points.len())
+            .filter(|i| !done[*i])
+            .min_by(|a, b| dist[*a].total_cmp(&dist[*b]))
+            .unwrap();
+        if dist[here].is_infinite() {
+            break;
+        }
+        done[here] = true;
+        for next in 0..points.len() {
+            if done[next] || !path_is_clear(points[here], points[next]) {
+                continue;
+            }
+            let d = dist[here] + (flat(points[next]) - flat(points[here])).mag();
+            if d < dist[next] {
+                dist[next] = d;
+                prev[next] = here;
+            }
+        }
+    }
+    if dist[goal].is_infinite() {
+        return target;
+    }
+    let mut step = goal;
+    while prev[step] != 0 {
+        step = prev[step];
+    }
+    return points[step];
+}
+
+//press whichever keys close the gap, one axis at a time being fine
+pub fn steer(pos: Vec3, target: Vec3, avoid_chest: bool, speed: f32) -> Controls {
+    let goal = if avoid_chest {
+        detour(pos, target)
+    } else {
+        target
+    };
+    let dx = goal.x - pos.x;
+    let dz = goal.z - pos.z;
+    return Controls {
+        up: dz < -speed / 2.0,
+        down: dz > speed / 2.0,
+        left: dx < -speed / 2.0,
+        right: dx > speed / 2.0,
+        ..Controls::default()
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{GhostRules, Levels};
+    use crate::map::Generator;
+    use crate::rules;
+    use crate::sim;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn detour_goes_around_the_chest() {
+        let pos = Vec3::new(0.0, 0.0, 20.0);
+        let target = Vec3::new(0.0, 0.0, -30.0);
+        assert!(!path_is_clear(pos, target));
+        let step = detour(pos, target);
+        assert!(path_is_clear(pos, step));
+        assert_eq!(step.z, CHEST_CORNER);
+    }
+
+    #[test]
+    fn ls_are_walked_round_by_the_elbow() {
+        let l = Shape {
+            width: 8,
+            depth: 8,
+            notch: Some(Corner::NorthEast),
+        };
+        let (west, south) = (Vec3::new(-20.0, 0.0, 20.0), Vec3::new(20.0, 0.0, -20.0));
+        assert!(cuts_corner(l, west, south));
+        let turn = elbow(l, 5.0);
+        assert!(!cuts_corner(l, west, turn) && !cuts_corner(l, turn, south));
+        //along the inner wall is fine
+        assert!(!cuts_corner(l, west, Vec3::new(0.0, 0.0, 15.0)));
+        assert!(!cuts_corner(Shape::SQUARE, west, south));
+    }
+
+    #[test]
+    fn clear_paths_go_straight() {
+        let pos = Vec3::new(20.0, 0.0, 0.0);
+        let target = Vec3::new(30.0, 0.0, 0.0);
+        assert_eq!(detour(pos, target), target);
+    }
+
+    #[test]
+    fn never_bumps_into_the_chest_while_exploring() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut state = GameState::generate(6, 3, 8, &mut rng);
+            let mut bot = Explorer::new();
+            for _ in 0..20_000 {
+                let controls = bot.controls(&state);
+                let events = rules::update(&mut state, controls, &mut rng);
+                assert!(!events.contains(&rules::Event::Blocked), "seed {}", seed);
+                if state.is_finished {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solves_every_house() {
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut state = GameState::generate(8, 4, 8, &mut rng);
+            let rooms = state.rooms.len();
+            let mut bot = Explorer::new();
+            let report = sim::run(&mut state, &mut bot, 100_000, &mut rng);
+            assert!(report.completed, "seed {} got stuck", seed);
+            assert!(bot.rooms_seen() <= rooms);
+        }
+    }
+
+    #[test]
+    fn solves_endless_levels_with_loops_and_locks() {
+        let levels = Levels::default();
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let rules = GhostRules::default();
+            let mut state =
+                GameState::for_level(levels, 12, Generator::Tree, 1, 8, rules, &mut rng);
+            state.ghosts.clear();
+            assert!(state.doors.iter().any(|door| door.locked), "seed {}", seed);
+            let mut bot = Explorer::new();
+            let report = sim::run(&mut state, &mut bot, 200_000, &mut rng);
+            assert!(report.completed, "seed {} got stuck", seed);
+        }
+    }
+
+    #[test]
+    fn walks_through_the_menus() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut state = GameState::generate(3, 0, 1, &mut rng);
+        state.gameplaystate = GameplayState::Mainscreen;
+        let mut bot = Explorer::new();
+        let report = sim::run(&mut state, &mut bot, 100_000, &mut rng);
+        assert!(report.completed);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::config::Tuning;
+use crate::geometry::*;
+use crate::types::*;
+use ultraviolet::Vec3;
+
+//axis aligned box test on the floor plane; y is ignored
+pub fn check_item_collisions(
+    pos: Vec3,
+    object: Vec3,
+    obj_edge_length_x: f32,
+    obj_edge_length_z: f32,
+) -> bool {
+    return pos.x <= object.x + obj_edge_length_x
+        && pos.x >= object.x - obj_edge_length_x
+        && pos.z <= object.z + obj_edge_length_z
+        && pos.z >= object.z - obj_edge_length_z;
+}
+
+pub fn check_collisions(pos: Vec3, door: Door, shape: Shape, tuning: &Tuning) -> bool {
+    let door_worldspace = door_trf(shape, door, tuning);
+    let half_buffer = tuning.buffer / 2.0;
+    let depth = tuning.door_depth * tuning.scale;
+    let reach = tuning.door_reach();
+    if door.direction == Direction::North {
+        return pos.z + half_buffer >= door_worldspace.translation.z - depth
+            && pos.x <= door_worldspace.translation.x + reach
+            && pos.x >= door_worldspace.translation.x - reach;
+    } else if door.direction == Direction::South {
+        return pos.z - half_buffer <= door_worldspace.translation.z + depth
+            && pos.x <= door_worldspace.translation.x + reach
+            && pos.x >= door_worldspace.translation.x - reach;
+    } else if door.direction == Direction::East {
+        return pos.x + half_buffer >= door_worldspace.translation.x - depth
+            && pos.z <= door_worldspace.translation.z + reach
+            && pos.z >= door_worldspace.translation.z - reach;
+    } else {
+        return pos.x - half_buffer <= door_worldspace.translation.x + depth
+            && pos.z <= door_worldspace.translation.z + reach
+            && pos.z >= door_worldspace.translation.z - reach;
+    }
+}
+
+//the player can only walk on the floor of the room, and not in the notch of an L
+pub fn in_room_bounds(pos: Vec3, shape: Shape, room_size: f32) -> bool {
+    let step = room_size / STEPS as f32;
+    let (half_x, half_z) = (
+        shape.width as f32 * step / 2.0,
+        shape.depth as f32 * step / 2.0,
+    );
+    let in_notch = shape.notch.is_some_and(|corner| {
+        let (x, z) = corner_signs(corner);
+        pos.x * x as f32 > 0.0 && pos.z * z as f32 > 0.0
+    });
+    return pos.x < half_x && pos.x > -half_x && pos.z < half_z && pos.z > -half_z && !in_notch;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn item_box_edges_are_inclusive() {
+        let item = Vec3::new(0.0, 5.0, 0.0);
+        assert!(check_item_collisions(
+            Vec3::new(7.75, 0.0, -7.75),
+            item,
+            7.75,
+            7.75
+        ));
+        assert!(!check_item_collisions(
+            Vec3::new(7.76, 0.0, 0.0),
+            item,
+            7.75,
+            7.75
+        ));
+        assert!(!check_item_collisions(
+            Vec3::new(0.0, 0.0, 3.5),
+            item,
+            7.75,
+            3.0
+        ));
+    }
+
+    #[test]
+    fn door_triggers_near_its_wall_only() {
+        let tuning = Tuning::default();
+        let square = Shape::SQUARE;
+        let north = Door::new(Direction::North, 1, Direction::South);
+        assert!(check_collisions(
+            Vec3::new(0.0, 0.0, 27.0),
+            north,
+            square,
+            &tuning
+        ));
+        assert!(!check_collisions(
+            Vec3::new(0.0, 0.0, 20.0),
+            north,
+            square,
+            &tuning
+        ));
+        assert!(!check_collisions(
+            Vec3::new(10.0, 0.0, 29.0),
+            north,
+            square,
+            &tuning
+        ));
+
+        let west = Door::new(Direction::West, 1, Direction::East);
+        assert!(check_collisions(
+            Vec3::new(-27.0, 0.0, 2.0),
+            west,
+            square,
+            &tuning
+        ));
+        assert!(!check_collisions(
+            Vec3::new(27.0, 0.0, 2.0),
+            west,
+            square,
+            &tuning
+        ));
+    }
+
+    #[test]
+    fn doors_off_the_middle_trigger_where_they_are() {
+        let tuning = Tuning::default();
+        let mut north = Door::new(Direction::North, 1, Direction::South);
+        north.offset = 2;
+        let corridor = Shape {
+            width: 8,
+            depth: 4,
+            notch: None,
+        };
+        assert!(check_collisions(
+            Vec3::new(15.0, 0.0, 13.0),
+            north,
+            corridor,
+            &tuning
+        ));
+        assert!(!check_collisions(
+            Vec3::new(0.0, 0.0, 13.0),
+            north,
+            corridor,
+            &tuning
+        ));
+        //and a square room's north wall is further out
+        assert!(!check_collisions(
+            Vec3::new(15.0, 0.0, 13.0),
+            north,
+            Shape::SQUARE,
+            &tuning
+        ));
+    }
+
+    #[test]
+    fn the_notch_of_an_l_is_off_the_floor() {
+        let l = Shape {
+            width: 8,
+            depth: 8,
+            notch: Some(Corner::NorthEast),
+        };
+        assert!(!in_room_bounds(Vec3::new(10.0, 0.0, 10.0), l, 60.0));
+        assert!(in_room_bounds(Vec3::new(-10.0, 0.0, 10.0), l, 60.0));
+        assert!(in_room_bounds(Vec3::new(10.0, 0.0, -10.0), l, 60.0));
+        //the inner walls themselves can be walked along
+        assert!(in_room_bounds(Vec3::new(0.0, 0.0, 10.0), l, 60.0));
+        assert!(in_room_bounds(Vec3::new(10.0, 0.0, 0.0), l, 60.0));
+        assert!(!in_room_bounds(Vec3::new(-10.0, 0.0, 31.0), l, 60.0));
+    }
+
+    #[test]
+    fn spawning_does_not_retrigger_the_door_back() {
+        for dir in [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ] {
+            let tuning = Tuning::default();
+            let back = Door::new(get_spawn_dir(dir), 0, dir);
+            assert!(!check_collisions(
+                get_spawn_pos(Shape::SQUARE, back, &tuning),
+                back,
+                Shape::SQUARE,
+                &tuning
+            ));
+        }
+    }
+}
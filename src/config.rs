@@ -0,0 +1,485 @@
+use crate::ghost::Catch;
+use crate::map::Generator;
+use crate::transition::Style;
+use crate::DT;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+// The numbers that decide how walking around the house feels. They ride along
+// on every GameState, and so in every replay, because changing any of them
+// changes where a run ends up.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tuning {
+    pub speed: f32,     //distance walked per tick
+    pub room_size: f32, //length of a wall of a square room
+    pub scale: f32,     //size of the door models
+    pub buffer: f32,    //how far inside the wall you come out of a door
+    pub door_width: f32,
+    pub door_depth: f32,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        return Tuning {
+            speed: 0.5,
+            room_size: 60.0,
+            scale: 10.0,
+            buffer: 5.0,
+            door_width: 0.177,
+            door_depth: 0.07,
+        };
+    }
+}
+
+impl Tuning {
+    //how far along its wall either side of the middle a door still catches you
+    pub fn door_reach(&self) -> f32 {
+        return self.door_width * self.scale * 3.0;
+    }
+}
+
+//the first house, and how each restart grows it
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct House {
+    pub rooms: usize, //rooms on the path to the key
+    pub difficulty: usize,
+    pub dead_ends: usize,
+    pub floors: usize, //storeys, joined by staircases on the way to the key
+    pub generator: Generator,
+}
+
+impl Default for House {
+    fn default() -> Self {
+        return House {
+            rooms: 4,
+            difficulty: 3,
+            dead_ends: 2,
+            floors: 1,
+            generator: Generator::Path,
+        };
+    }
+}
+
+//how the front end moves between screens and between rooms
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Transitions {
+    pub screen: Style,
+    pub screen_time: f64, //seconds
+    pub room: Style,
+    pub room_time: f64, //seconds, and also how long a door takes to swing
+}
+
+impl Default for Transitions {
+    fn default() -> Self {
+        return Transitions {
+            screen: Style::Fade,
+            screen_time: 0.6,
+            room: Style::Fade,
+            room_time: 0.6,
+        };
+    }
+}
+
+// The ghosts that roam the house. Like the tuning they change where a run
+// ends up, so they ride along on every GameState and in every replay. Speeds
+// are shares of the player's walking speed.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GhostRules {
+    pub count: usize,   //ghosts in every house; none in the classic game
+    pub speed: f32,     //in the first house
+    pub speedup: f32,   //added for every house cleared since
+    pub max_speed: f32, //however many houses that is
+    pub linger: f32,    //seconds a ghost waits in a room before drifting on
+    pub reach: f32,     //how close it has to get to catch you
+    pub catch: Catch,
+}
+
+impl Default for GhostRules {
+    fn default() -> Self {
+        return GhostRules {
+            count: 0,
+            speed: 0.5,
+            speedup: 0.1,
+            max_speed: 0.9,
+            linger: 3.0,
+            reach: 4.0,
+            catch: Catch::Start,
+        };
+    }
+}
+
+// How one number in the endless game changes with the level: start on the
+// first level, step more on every level after, and never outside min..=max.
+// Counts are the number rounded down, so a step of 0.25 adds one every four
+// levels.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Curve {
+    pub start: f32,
+    pub step: f32, //may be negative, for timers that get tighter
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        return Curve {
+            start: 0.0,
+            step: 0.0,
+            min: 0.0,
+            max: 0.0,
+        };
+    }
+}
+
+impl Curve {
+    pub fn at(&self, level: usize) -> f32 {
+        let value = self.start + self.step * level.saturating_sub(1) as f32;
+        return value.min(self.max).max(self.min);
+    }
+
+    pub fn count(&self, level: usize) -> usize {
+        return self.at(level) as usize;
+    }
+}
+
+// The endless game: every chest opened is a level up, and each of these
+// curves says what the house on a level holds. Floors and the generator
+// still come from the house section.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Levels {
+    pub rooms: Curve, //rooms on the path to the key
+    pub dead_ends: Curve,
+    pub loops: Curve,  //doors between rooms side by side that make another way round
+    pub locked: Curve, //doors off the way to the key that stay shut until it is found
+    pub ghosts: Curve, //how many; their speed follows the ghosts section
+    pub time_limit: Curve, //seconds to open the chest in, 0 for no limit
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        let curve = |start, step, max| Curve {
+            start,
+            step,
+            min: 0.0,
+            max,
+        };
+        return Levels {
+            rooms: Curve {
+                min: 1.0,
+                ..curve(4.0, 2.0, 40.0)
+            },
+            dead_ends: curve(2.0, 0.5, 12.0),
+            loops: curve(0.0, 0.5, 6.0),
+            locked: curve(0.0, 0.25, 4.0),
+            ghosts: curve(0.0, 0.25, 3.0),
+            time_limit: Curve::default(),
+        };
+    }
+}
+
+//the house on one level of the endless game
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Stage {
+    pub rooms: usize,
+    pub dead_ends: usize,
+    pub loops: usize,
+    pub locked: usize,
+    pub ghosts: usize,
+    pub time_limit: Option<u64>, //ticks
+}
+
+impl Levels {
+    pub fn stage(&self, level: usize) -> Stage {
+        let seconds = self.time_limit.at(level) as f64;
+        return Stage {
+            rooms: self.rooms.count(level).max(1),
+            dead_ends: self.dead_ends.count(level),
+            loops: self.loops.count(level),
+            locked: self.locked.count(level),
+            ghosts: self.ghosts.count(level),
+            time_limit: (seconds > 0.0).then_some((seconds / DT).round() as u64),
+        };
+    }
+}
+
+//help for players who get lost
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hints {
+    pub mark_dead_ends: bool, //mark doors with nothing left to find behind them
+}
+
+// Everything in config.toml. Missing sections and keys fall back to the
+// defaults above, so an empty file is the classic game.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub tuning: Tuning,
+    pub house: House,
+    pub transitions: Transitions,
+    pub hints: Hints,
+    pub ghosts: GhostRules,
+    pub levels: Levels,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let config: Config =
+            toml::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        config.validate()?;
+        return Ok(config);
+    }
+
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        return Config::parse(&text);
+    }
+
+    //a missing file is fine, a broken one is not
+    pub fn load_or_default(path: &Path) -> Result<Config, ConfigError> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        return Config::load(path);
+    }
+
+    //reports every problem at once so a designer can fix the file in one go
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = vec![];
+        let tuning = &self.tuning;
+        for (name, value) in [
+            ("speed", tuning.speed),
+            ("room_size", tuning.room_size),
+            ("scale", tuning.scale),
+            ("buffer", tuning.buffer),
+            ("door_width", tuning.door_width),
+            ("door_depth", tuning.door_depth),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                problems.push(format!("tuning.{} must be positive, not {}", name, value));
+            }
+        }
+        //doors can sit a step from a corner, and a step is an eighth of a room,
+        //so coming out of one has to stay clear of the door round the corner
+        if tuning.buffer >= tuning.room_size / 8.0 {
+            problems
+                .push("tuning.buffer must be less than an eighth of tuning.room_size".to_string());
+        }
+        if tuning.door_reach() >= tuning.room_size / 8.0 {
+            problems.push("doors are wider than the narrowest walls they sit on".to_string());
+        }
+        //otherwise coming out of a door lands you in the one on that wall
+        if tuning.door_depth * tuning.scale >= tuning.buffer / 2.0 {
+            problems.push(
+                "tuning.door_depth * tuning.scale must be less than half of tuning.buffer"
+                    .to_string(),
+            );
+        }
+        for (name, value) in [
+            ("screen_time", self.transitions.screen_time),
+            ("room_time", self.transitions.room_time),
+        ] {
+            if !(value.is_finite() && value >= 0.0) {
+                problems.push(format!(
+                    "transitions.{} must not be negative, not {}",
+                    name, value
+                ));
+            }
+        }
+        if self.house.rooms == 0 {
+            problems.push("house.rooms must be at least 1".to_string());
+        }
+        if self.house.floors == 0 {
+            problems.push("house.floors must be at least 1".to_string());
+        }
+        let ghosts = &self.ghosts;
+        for (name, value) in [
+            ("speed", ghosts.speed),
+            ("max_speed", ghosts.max_speed),
+            ("reach", ghosts.reach),
+        ] {
+            if !(value.is_finite() && value > 0.0) {
+                problems.push(format!("ghosts.{} must be positive, not {}", name, value));
+            }
+        }
+        for (name, value) in [("speedup", ghosts.speedup), ("linger", ghosts.linger)] {
+            if !(value.is_finite() && value >= 0.0) {
+                problems.push(format!(
+                    "ghosts.{} must not be negative, not {}",
+                    name, value
+                ));
+            }
+        }
+        let levels = &self.levels;
+        for (name, curve) in [
+            ("rooms", levels.rooms),
+            ("dead_ends", levels.dead_ends),
+            ("loops", levels.loops),
+            ("locked", levels.locked),
+            ("ghosts", levels.ghosts),
+            ("time_limit", levels.time_limit),
+        ] {
+            let values = [curve.start, curve.step, curve.min, curve.max];
+            if !values.iter().all(|value| value.is_finite()) {
+                problems.push(format!("levels.{} must be all numbers", name));
+            } else if !(0.0 <= curve.min && curve.min <= curve.max) {
+                problems.push(format!(
+                    "levels.{} needs 0 <= min <= max, not {} and {}",
+                    name, curve.min, curve.max
+                ));
+            }
+        }
+        if levels.rooms.max < 1.0 {
+            problems.push("levels.rooms.max must be at least 1".to_string());
+        }
+        if !problems.is_empty() {
+            return Err(ConfigError::Invalid(problems));
+        }
+        return Ok(());
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(String),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{}", err),
+            ConfigError::Parse(message) => write!(f, "{}", message),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid config:")?;
+                for problem in problems.iter() {
+                    write!(f, "\n  {}", problem)?;
+                }
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_empty_file_is_the_classic_game() {
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn the_shipped_file_loads() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("config.toml");
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn keys_override_only_themselves() {
+        let config =
+            Config::parse("[tuning]\nspeed = 0.75\n[house]\ngenerator = \"tree\"\n").unwrap();
+        assert_eq!(config.tuning.speed, 0.75);
+        assert_eq!(config.tuning.room_size, Tuning::default().room_size);
+        assert_eq!(config.house.generator, Generator::Tree);
+        assert_eq!(config.house.rooms, House::default().rooms);
+    }
+
+    #[test]
+    fn typos_are_errors_not_silently_ignored() {
+        let err = Config::parse("[tuning]\nsped = 0.75\n").unwrap_err();
+        assert!(err.to_string().contains("unknown field `sped`"), "{}", err);
+        let err = Config::parse("[tuning]\nspeed = \"fast\"\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{}", err);
+    }
+
+    #[test]
+    fn transition_styles_are_named_in_lowercase() {
+        let config =
+            Config::parse("[transitions]\nscreen = \"crossfade\"\nroom = \"cut\"\n").unwrap();
+        assert_eq!(config.transitions.screen, Style::CrossFade);
+        assert_eq!(config.transitions.room, Style::Cut);
+        assert!(Config::parse("[transitions]\nroom = \"dissolve\"\n").is_err());
+        assert!(Config::parse("[transitions]\nroom_time = -1.0\n").is_err());
+    }
+
+    #[test]
+    fn ghosts_are_off_unless_asked_for() {
+        assert_eq!(Config::default().ghosts.count, 0);
+        let config = Config::parse("[ghosts]\ncount = 2\ncatch = \"end\"\n").unwrap();
+        assert_eq!(config.ghosts.count, 2);
+        assert_eq!(config.ghosts.catch, Catch::End);
+        assert!(Config::parse("[ghosts]\ncatch = \"eat\"\n").is_err());
+        assert!(Config::parse("[ghosts]\nspeed = 0.0\n").is_err());
+    }
+
+    #[test]
+    fn curves_step_with_the_level_between_their_bounds() {
+        let curve = Curve {
+            start: 1.0,
+            step: 0.5,
+            min: 0.0,
+            max: 3.0,
+        };
+        let counts: Vec<usize> = (1..=7).map(|level| curve.count(level)).collect();
+        assert_eq!(counts, [1, 1, 2, 2, 3, 3, 3]);
+        let tighter = Curve {
+            start: 90.0,
+            step: -10.0,
+            min: 30.0,
+            max: 90.0,
+        };
+        assert_eq!(
+            (tighter.at(1), tighter.at(4), tighter.at(20)),
+            (90.0, 60.0, 30.0)
+        );
+
+        let levels = Levels {
+            time_limit: tighter,
+            ..Levels::default()
+        };
+        let first = levels.stage(1);
+        assert_eq!((first.rooms, first.loops, first.ghosts), (4, 0, 0));
+        assert_eq!(first.time_limit, Some(90 * 60));
+        assert_eq!(Levels::default().stage(1).time_limit, None);
+        assert!(levels.stage(100).rooms <= 40);
+    }
+
+    #[test]
+    fn level_curves_read_as_inline_tables() {
+        let config =
+            Config::parse("[levels]\nloops = { start = 1.0, step = 1.0, max = 2.0 }\n").unwrap();
+        assert_eq!(config.levels.loops.count(5), 2);
+        assert_eq!(config.levels.rooms, Levels::default().rooms);
+        assert!(Config::parse("[levels]\nghosts = { min = 2.0, max = 1.0 }\n").is_err());
+        assert!(Config::parse("[levels]\nrooms = { max = 0.0 }\n").is_err());
+    }
+
+    #[test]
+    fn every_bad_value_is_reported() {
+        let err = Config::parse(
+            "[tuning]\nspeed = -1.0\nbuffer = 40.0\n[house]\nrooms = 0\nfloors = 0\n",
+        )
+        .unwrap_err();
+        match err {
+            ConfigError::Invalid(problems) => {
+                assert_eq!(problems.len(), 4, "{:?}", problems);
+                assert!(problems[0].starts_with("tuning.speed"));
+            }
+            other => panic!("expected invalid, got {:?}", other),
+        }
+    }
+}
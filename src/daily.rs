@@ -0,0 +1,181 @@
+use crate::config::{GhostRules, House, Tuning};
+use crate::map::Generator;
+use crate::replay::Setup;
+use crate::save::SaveFile;
+use crate::state::GameplayState;
+use chrono::{Datelike, NaiveDate};
+
+// The daily house: one house a day, the same for everyone whatever their
+// config says, played against the clock. Its seed is the date written out,
+// 20261019 for the 19th of October 2026, so an old one can be played again
+// with --seed and these settings.
+pub const HOUSE: House = House {
+    rooms: 7,
+    difficulty: 3,
+    dead_ends: 3,
+    floors: 1,
+    generator: Generator::Tree,
+};
+
+// Rooms are painted from this many themes whatever is installed, and the game
+// wraps them onto the themes it has, so a new theme never changes a day's house.
+pub const THEMES: usize = 8;
+
+//the date where the game is being played, which is what makes it the same day for everyone
+pub fn today() -> NaiveDate {
+    return chrono::Local::now().date_naive();
+}
+
+pub fn seed(date: NaiveDate) -> u64 {
+    return date.year() as u64 * 10000 + date.month() as u64 * 100 + date.day() as u64;
+}
+
+pub fn setup(date: NaiveDate) -> Setup {
+    return Setup {
+        seed: seed(date),
+        rooms: HOUSE.rooms,
+        dead_ends: HOUSE.dead_ends,
+        floors: HOUSE.floors,
+        difficulty: HOUSE.difficulty,
+        generator: HOUSE.generator,
+        num_tex: THEMES,
+        key_index: HOUSE.rooms - 1,
+        start: GameplayState::TimeAttack,
+        tuning: Tuning::default(),
+        ghosts: GhostRules::default(),
+        levels: None,
+        level: 1,
+    };
+}
+
+// Every daily house opened, kept in a local file between sessions, one line
+// of date and ticks per attempt in the order they were played.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct History {
+    attempts: Vec<Attempt>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Attempt {
+    pub date: NaiveDate,
+    pub ticks: u64,
+}
+
+impl History {
+    pub fn record(&mut self, date: NaiveDate, ticks: u64) {
+        self.attempts.push(Attempt { date, ticks });
+    }
+
+    pub fn tries_on(&self, date: NaiveDate) -> usize {
+        return self.on(date).count();
+    }
+
+    pub fn best_on(&self, date: NaiveDate) -> Option<u64> {
+        return self.on(date).map(|attempt| attempt.ticks).min();
+    }
+
+    //the best of every day played before date, latest first
+    pub fn days_before(&self, date: NaiveDate) -> Vec<Attempt> {
+        let mut days: Vec<NaiveDate> = self
+            .attempts
+            .iter()
+            .map(|attempt| attempt.date)
+            .filter(|day| *day < date)
+            .collect();
+        days.sort_unstable_by(|a, b| b.cmp(a));
+        days.dedup();
+        return days
+            .into_iter()
+            .map(|day| Attempt {
+                date: day,
+                ticks: self.best_on(day).unwrap(),
+            })
+            .collect();
+    }
+
+    fn on(&self, date: NaiveDate) -> impl Iterator<Item = &Attempt> + '_ {
+        return self
+            .attempts
+            .iter()
+            .filter(move |attempt| attempt.date == date);
+    }
+}
+
+impl SaveFile for History {
+    const HEADER: &'static str = "scene3d daily houses: date, ticks";
+    const KIND: &'static str = "a daily history";
+
+    fn records(&self) -> Vec<String> {
+        return self
+            .attempts
+            .iter()
+            .map(|attempt| format!("{} {}", attempt.date, attempt.ticks))
+            .collect();
+    }
+
+    fn read(&mut self, words: &[&str]) -> Option<()> {
+        if words.len() != 2 {
+            return None;
+        }
+        self.attempts.push(Attempt {
+            date: NaiveDate::parse_from_str(words[0], "%Y-%m-%d").ok()?,
+            ticks: words[1].parse().ok()?,
+        });
+        return Some(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        return NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+    }
+
+    #[test]
+    fn the_seed_is_the_date_written_out() {
+        assert_eq!(seed(day(19)), 20261019);
+        assert_eq!(seed(NaiveDate::from_ymd_opt(2027, 1, 2).unwrap()), 20270102);
+    }
+
+    #[test]
+    fn everyone_gets_the_same_house_on_the_same_day() {
+        let (today, _) = setup(day(19)).build();
+        let (again, _) = setup(day(19)).build();
+        let (tomorrow, _) = setup(day(20)).build();
+        assert_eq!(today, again);
+        assert_ne!(today.doors, tomorrow.doors);
+        assert_eq!(today.gameplaystate, GameplayState::TimeAttack);
+        assert_eq!(today.rooms.len(), HOUSE.rooms + HOUSE.dead_ends);
+    }
+
+    #[test]
+    fn earlier_days_come_latest_first_with_their_best() {
+        let mut history = History::default();
+        history.record(day(12), 900);
+        history.record(day(18), 700);
+        history.record(day(12), 800);
+        history.record(day(19), 750);
+        history.record(day(19), 650);
+        assert_eq!(
+            (history.tries_on(day(19)), history.best_on(day(19))),
+            (2, Some(650))
+        );
+        assert_eq!(history.best_on(day(13)), None);
+        let before = history.days_before(day(19));
+        let days: Vec<(NaiveDate, u64)> = before.iter().map(|a| (a.date, a.ticks)).collect();
+        assert_eq!(days, [(day(18), 700), (day(12), 800)]);
+    }
+
+    #[test]
+    fn histories_survive_the_round_trip_to_text() {
+        let mut history = History::default();
+        history.record(day(18), 700);
+        history.record(day(19), 61234);
+        assert!(history.to_text().contains("2026-10-19 61234\n"));
+        assert_eq!(History::parse(&history.to_text()).unwrap(), history);
+        assert!(History::parse("2026-10-19\n").is_err());
+        assert!(History::parse("19/10/2026 700\n").is_err());
+    }
+}
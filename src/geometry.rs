@@ -0,0 +1,377 @@
+use crate::config::Tuning;
+use crate::types::*;
+use std::f32::consts::PI;
+use ultraviolet::{Rotor3, Similarity3, Vec3};
+
+//where a door sits in room space for a given wall
+pub fn get_trf(dir: Direction, room_size: f32, scale: f32) -> Similarity3 {
+    match dir {
+        Direction::North => Similarity3::new(
+            Vec3::new(0.0, 0.0, room_size / 2.),
+            Rotor3::from_euler_angles(0.0, -PI / 2.0, 0.0),
+            scale,
+        ),
+        Direction::East => Similarity3::new(
+            Vec3::new(room_size / 2., 0.0, 0.0),
+            Rotor3::from_euler_angles(PI / 2.0, -PI / 2.0, 0.0),
+            scale,
+        ),
+        Direction::South => Similarity3::new(
+            Vec3::new(0.0, 0.0, -room_size / 2.),
+            Rotor3::from_euler_angles(0.0, -PI / 2.0, 0.0),
+            scale,
+        ),
+        Direction::West => Similarity3::new(
+            Vec3::new(-room_size / 2., 0.0, 0.0),
+            Rotor3::from_euler_angles(-PI / 2.0, -PI / 2.0, 0.0),
+            scale,
+        ),
+        // Direction::Other(n) => n as usize,
+    }
+}
+
+pub fn get_dir(num: u32) -> Direction {
+    match num {
+        0 => Direction::North,
+        1 => Direction::East,
+        2 => Direction::South,
+        3 => Direction::West,
+        _other => Direction::West,
+    }
+}
+
+//the wall you come out of after walking through a door facing dir
+pub fn get_spawn_dir(dir: Direction) -> Direction {
+    match dir {
+        Direction::North => Direction::South,
+        Direction::South => Direction::North,
+        Direction::East => Direction::West,
+        Direction::West => Direction::East,
+    }
+}
+
+// Where you come out of a door: in front of it, a buffer in from its wall so
+// you don't walk straight back through.
+pub fn get_spawn_pos(shape: Shape, door: Door, tuning: &Tuning) -> Vec3 {
+    return door_pos(shape, door, tuning.room_size) + get_inward(door.direction) * tuning.buffer;
+}
+
+//the way into the room from a wall facing dir
+pub fn get_inward(dir: Direction) -> Vec3 {
+    let out = get_offset(dir);
+    return Vec3::new(-out.x as f32, 0.0, -out.y as f32);
+}
+
+//which way along x and z a corner lies from the middle of the room
+pub fn corner_signs(corner: Corner) -> (i32, i32) {
+    match corner {
+        Corner::NorthEast => (1, 1),
+        Corner::SouthEast => (1, -1),
+        Corner::SouthWest => (-1, -1),
+        Corner::NorthWest => (-1, 1),
+    }
+}
+
+// One straight stretch of a room's wall, in steps. It faces `direction`,
+// stands `across` steps out from the middle that way, and runs from `from` to
+// `to` along x for north and south walls and along z for east and west ones.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Wall {
+    pub direction: Direction,
+    pub across: i32,
+    pub from: i32,
+    pub to: i32,
+}
+
+// The walls of a shape. A box has one per direction; the notch of an L halves
+// the two outer walls at its corner and adds the two inner walls that meet in
+// the middle of the room.
+pub fn walls(shape: Shape) -> Vec<Wall> {
+    let mut walls = vec![];
+    for direction in [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ] {
+        let out = get_offset(direction);
+        //the wall's own sign, then the half extents across and along it
+        let (facing, across, along) = if out.x == 0 {
+            (out.y, shape.depth / 2, shape.width / 2)
+        } else {
+            (out.x, shape.width / 2, shape.depth / 2)
+        };
+        let notch = shape
+            .notch
+            .map(corner_signs)
+            .map(|(x, z)| if out.x == 0 { (z, x) } else { (x, z) });
+        match notch {
+            //the notch's side of this wall is pulled in to the middle
+            Some((notch_facing, notch_along)) if notch_facing == facing => {
+                let (outer, inner) = if notch_along > 0 {
+                    ((-along, 0), (0, along))
+                } else {
+                    ((0, along), (-along, 0))
+                };
+                walls.push(Wall {
+                    direction,
+                    across,
+                    from: outer.0,
+                    to: outer.1,
+                });
+                walls.push(Wall {
+                    direction,
+                    across: 0,
+                    from: inner.0,
+                    to: inner.1,
+                });
+            }
+            _ => walls.push(Wall {
+                direction,
+                across,
+                from: -along,
+                to: along,
+            }),
+        }
+    }
+    return walls;
+}
+
+// Offsets a door facing dir can take: its middle a step or more from either
+// end of an outer wall. The inner walls of an L meet in the middle of the
+// room, where the key floats, so doors there would catch anyone fetching it.
+pub fn door_slots(shape: Shape, dir: Direction) -> Vec<i32> {
+    let mut slots = vec![];
+    let outer = walls(shape)
+        .into_iter()
+        .filter(|wall| wall.direction == dir && wall.across != 0);
+    for wall in outer {
+        slots.extend(wall.from + 1..wall.to);
+    }
+    return slots;
+}
+
+//how far out from the middle the wall facing dir stands at a point along it
+pub fn wall_at(shape: Shape, dir: Direction, along: f32) -> f32 {
+    let walls = walls(shape);
+    let mut facing = walls.iter().filter(|wall| wall.direction == dir);
+    //a point right on the corner of an L counts as on the outer wall
+    let wall = facing
+        .clone()
+        .find(|wall| wall.from as f32 <= along && along <= wall.to as f32 && wall.across != 0)
+        .or_else(|| facing.find(|wall| wall.from as f32 <= along && along <= wall.to as f32))
+        .unwrap_or(&walls[0]);
+    return wall.across as f32;
+}
+
+//the middle of a door at the foot of its wall, in room space
+pub fn door_pos(shape: Shape, door: Door, room_size: f32) -> Vec3 {
+    let step = room_size / STEPS as f32;
+    let along = door.offset as f32 * step;
+    let across = wall_at(shape, door.direction, door.offset as f32) * step;
+    return match door.direction {
+        Direction::North => Vec3::new(along, 0.0, across),
+        Direction::South => Vec3::new(along, 0.0, -across),
+        Direction::East => Vec3::new(across, 0.0, along),
+        Direction::West => Vec3::new(-across, 0.0, along),
+    };
+}
+
+//where a door sits in a room of the given shape, turned like get_trf's
+pub fn door_trf(shape: Shape, door: Door, tuning: &Tuning) -> Similarity3 {
+    let mut trf = get_trf(door.direction, tuning.room_size, tuning.scale);
+    trf.translation = door_pos(shape, door, tuning.room_size);
+    return trf;
+}
+
+//the neighbouring grid cell through a wall; north is +z like get_trf
+pub fn get_offset(dir: Direction) -> Vec2i {
+    match dir {
+        Direction::North => Vec2i { x: 0, y: 1 },
+        Direction::East => Vec2i { x: 1, y: 0 },
+        Direction::South => Vec2i { x: 0, y: -1 },
+        Direction::West => Vec2i { x: -1, y: 0 },
+    }
+}
+
+//rotation of the character model when walking in a direction
+pub fn get_facing(dir: Direction) -> Rotor3 {
+    match dir {
+        Direction::North => Rotor3::from_euler_angles(0.0, 0.0, PI),
+        Direction::East => Rotor3::from_euler_angles(0.0, 0.0, -PI / 2.0),
+        Direction::South => Rotor3::from_euler_angles(0.0, 0.0, 0.0),
+        Direction::West => Rotor3::from_euler_angles(0.0, 0.0, PI / 2.0),
+    }
+}
+
+// A door's transform swung `amount` of the way open (0 shut, 1 square to the
+// wall) about the hinge half_width along the wall from its middle. It always
+// swings into the room, whichever way that turns for this wall.
+pub fn swing_door(trf: Similarity3, dir: Direction, half_width: f32, amount: f32) -> Similarity3 {
+    let along = match dir {
+        Direction::North | Direction::South => Vec3::unit_x(),
+        Direction::East | Direction::West => Vec3::unit_z(),
+    };
+    let hinge = trf.translation + along * half_width;
+    let turn = |angle: f32| {
+        let rotor = Rotor3::from_rotation_xz(angle);
+        let mut swung = trf;
+        swung.translation = hinge + (trf.translation - hinge).rotated_by(rotor);
+        swung.rotation = rotor * trf.rotation;
+        return swung;
+    };
+    let angle = amount.clamp(0.0, 1.0) * PI / 2.0;
+    let (one_way, other_way) = (turn(angle), turn(-angle));
+    let inward = get_inward(dir);
+    if (one_way.translation - trf.translation).dot(inward)
+        >= (other_way.translation - trf.translation).dot(inward)
+    {
+        return one_way;
+    }
+    return other_way;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    #[test]
+    fn spawn_dir_is_an_involution() {
+        for dir in ALL {
+            assert_ne!(get_spawn_dir(dir), dir);
+            assert_eq!(get_spawn_dir(get_spawn_dir(dir)), dir);
+        }
+    }
+
+    #[test]
+    fn doors_sit_on_the_walls() {
+        let tuning = Tuning::default();
+        for dir in ALL {
+            let t = get_trf(dir, tuning.room_size, tuning.scale).translation;
+            assert_eq!(t.x.abs().max(t.z.abs()), tuning.room_size / 2.0);
+            assert_eq!(t.y, 0.0);
+        }
+    }
+
+    #[test]
+    fn spawn_is_buffered_inside_the_opposite_wall() {
+        let tuning = Tuning::default();
+        let half = tuning.room_size / 2.0;
+        for dir in ALL {
+            let back = Door::new(get_spawn_dir(dir), 0, dir);
+            let spawn = get_spawn_pos(Shape::SQUARE, back, &tuning);
+            let wall = get_trf(get_spawn_dir(dir), tuning.room_size, tuning.scale).translation;
+            assert!(((spawn - wall).mag() - tuning.buffer).abs() < 1e-4);
+            assert!(spawn.x.abs() < half && spawn.z.abs() < half);
+        }
+    }
+
+    #[test]
+    fn doors_swing_about_the_hinge_into_the_room() {
+        let tuning = Tuning::default();
+        let half_width = tuning.door_reach();
+        for dir in ALL {
+            let shut = get_trf(dir, tuning.room_size, tuning.scale);
+            assert_eq!(swing_door(shut, dir, half_width, 0.0), shut);
+            let open = swing_door(shut, dir, half_width, 1.0);
+            //the middle of the door ends up half a door in from the hinge
+            let along = if shut.translation.x == 0.0 {
+                Vec3::unit_x()
+            } else {
+                Vec3::unit_z()
+            };
+            let hinge = shut.translation + along * half_width;
+            let inward = -shut.translation.normalized() * half_width;
+            assert!(
+                (open.translation - (hinge + inward)).mag() < 1e-3,
+                "{:?}",
+                dir
+            );
+            assert_eq!(open.scale, shut.scale);
+        }
+    }
+
+    #[test]
+    fn a_box_has_one_wall_per_side() {
+        let corridor = Shape {
+            width: 4,
+            depth: 8,
+            notch: None,
+        };
+        let walls = walls(corridor);
+        assert_eq!(walls.len(), 4);
+        assert_eq!(
+            walls[1],
+            Wall {
+                direction: Direction::East,
+                across: 2,
+                from: -4,
+                to: 4
+            }
+        );
+        assert_eq!(door_slots(corridor, Direction::North), [-1, 0, 1]);
+        assert_eq!(door_slots(corridor, Direction::West).len(), 7);
+    }
+
+    #[test]
+    fn an_l_has_inner_walls_that_meet_in_the_middle() {
+        let l = Shape {
+            width: 8,
+            depth: 6,
+            notch: Some(Corner::NorthEast),
+        };
+        let walls = walls(l);
+        assert_eq!(walls.len(), 6);
+        let inner: Vec<&Wall> = walls.iter().filter(|wall| wall.across == 0).collect();
+        assert_eq!(inner.len(), 2);
+        assert!(inner.iter().all(|wall| wall.from == 0));
+        //doors keep to the outer walls
+        assert_eq!(door_slots(l, Direction::North), [-3, -2, -1]);
+        assert_eq!(wall_at(l, Direction::North, -2.0), 3.0);
+        assert_eq!(wall_at(l, Direction::North, 2.0), 0.0);
+        assert_eq!(wall_at(l, Direction::South, 2.0), 3.0);
+        assert_eq!(door_slots(l, Direction::East), [-2, -1]);
+    }
+
+    #[test]
+    fn doors_and_spawns_follow_the_wall() {
+        let tuning = Tuning::default();
+        let l = Shape {
+            width: 8,
+            depth: 8,
+            notch: Some(Corner::SouthWest),
+        };
+        let mut west = Door::new(Direction::West, 0, Direction::East);
+        west.offset = -2;
+        //on the inner wall, a quarter of the way down
+        assert_eq!(
+            door_pos(l, west, tuning.room_size),
+            Vec3::new(0.0, 0.0, -15.0)
+        );
+        assert_eq!(
+            get_spawn_pos(l, west, &tuning),
+            Vec3::new(tuning.buffer, 0.0, -15.0)
+        );
+        west.offset = 2;
+        assert_eq!(
+            door_pos(l, west, tuning.room_size),
+            Vec3::new(-30.0, 0.0, 15.0)
+        );
+        let trf = door_trf(l, west, &tuning);
+        assert_eq!(trf.rotation, get_trf(Direction::West, 60.0, 10.0).rotation);
+    }
+
+    #[test]
+    fn get_dir_covers_every_wall() {
+        for (n, dir) in ALL.iter().enumerate() {
+            assert_eq!(get_dir(n as u32), *dir);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::config::GhostRules;
+use crate::geometry::*;
+use crate::nav::NavGrid;
+use crate::state::GameState;
+use crate::DT;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use ultraviolet::Vec3;
+
+//what happens when a ghost reaches the player
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Catch {
+    End,   //the run is over, and the next house is no bigger
+    Start, //back to the first room, key and all
+}
+
+impl fmt::Display for Catch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Catch::End => write!(f, "end"),
+            Catch::Start => write!(f, "start"),
+        };
+    }
+}
+
+impl FromStr for Catch {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        return match name {
+            "end" => Ok(Catch::End),
+            "start" => Ok(Catch::Start),
+            _ => Err(format!("unknown catch {} (expected end or start)", name)),
+        };
+    }
+}
+
+// A ghost drifting about the house. Inside a room it finds its way round the
+// furniture, it only gets from room to room by the doors the player could
+// take, and it never comes into the first room, so the chest is always safe.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Ghost {
+    pub room: usize,
+    pub pos: Vec3,
+    pub home: usize,            //where it goes back to after catching someone
+    pub heading: Option<usize>, //the door it is drifting to, once it has picked one
+    pub wait: u32,              //ticks before it picks one
+}
+
+impl Ghost {
+    //in the middle of its home, lingering before it sets off
+    pub fn at_home(home: usize, rules: &GhostRules) -> Self {
+        return Ghost {
+            room: home,
+            pos: Vec3::zero(),
+            home,
+            heading: None,
+            wait: linger_ticks(rules),
+        };
+    }
+}
+
+//count ghosts, each at home in a room that is neither the first nor the key's
+pub fn spawn<R: Rng>(state: &GameState, count: usize, rng: &mut R) -> Vec<Ghost> {
+    let homes: Vec<usize> = (1..state.rooms.len())
+        .filter(|room| *room != state.key_index)
+        .collect();
+    if homes.is_empty() {
+        return vec![];
+    }
+    return (0..count)
+        .map(|_| Ghost::at_home(homes[rng.gen_range(0..homes.len())], &state.ghost_rules))
+        .collect();
+}
+
+pub fn linger_ticks(rules: &GhostRules) -> u32 {
+    return (rules.linger as f64 / DT) as u32;
+}
+
+//distance a ghost drifts per tick, faster for every house cleared up to the cap
+pub fn speed(state: &GameState) -> f32 {
+    let rules = &state.ghost_rules;
+    let share = (rules.speed + rules.speedup * state.cleared as f32).min(rules.max_speed);
+    return state.tuning.speed * share;
+}
+
+// Moves every ghost one tick and says whether one reached the player. A ghost
+// in the player's room makes for them. The rest wait out their linger,
+// drift to a door picked at random and come out of the door back.
+pub fn step<R: Rng>(state: &mut GameState, rng: &mut R) -> bool {
+    let speed = speed(state);
+    let rules = state.ghost_rules;
+    let radius = state.tuning.buffer / 2.0;
+    let mut caught = false;
+    for i in 0..state.ghosts.len() {
+        let mut ghost = state.ghosts[i];
+        let grid = || NavGrid::for_room(state, ghost.room, radius);
+        if ghost.room == state.current_room {
+            ghost.heading = None;
+            ghost.pos = grid().steer(ghost.pos, state.player.pos, speed);
+            caught |= flat(ghost.pos - state.player.pos).mag() < rules.reach;
+        } else if let Some(dooridx) = ghost.heading {
+            let door = state.doors[dooridx];
+            let shape = state.rooms[ghost.room].shape;
+            let target = door_pos(shape, door, state.tuning.room_size);
+            ghost.pos = grid().steer(ghost.pos, target, speed);
+            if ghost.pos == target {
+                let back = state.door_back(ghost.room, door);
+                ghost.room = door.target;
+                ghost.pos = get_spawn_pos(state.rooms[door.target].shape, back, &state.tuning);
+                ghost.heading = None;
+                ghost.wait = linger_ticks(&rules);
+            }
+        } else if ghost.wait > 0 {
+            ghost.wait -= 1;
+        } else {
+            let ways: Vec<usize> = state
+                .room_doors(ghost.room)
+                .filter(|(_, door)| door.target != 0 && (!door.locked || state.has_key))
+                .map(|(dooridx, _)| dooridx)
+                .collect();
+            if ways.is_empty() {
+                ghost.wait = linger_ticks(&rules);
+            } else {
+                ghost.heading = Some(ways[rng.gen_range(0..ways.len())]);
+            }
+        }
+        state.ghosts[i] = ghost;
+    }
+    return caught;
+}
+
+fn flat(v: Vec3) -> Vec3 {
+    return Vec3::new(v.x, 0.0, v.z);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Generator;
+    use crate::state::START_POS;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn haunted(seed: u64, count: usize) -> (GameState, StdRng) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut state = GameState::generate_with(Generator::Tree, 8, 4, 1, 4, &mut rng);
+        let rules = GhostRules {
+            count,
+            linger: 0.5,
+            ..GhostRules::default()
+        };
+        state.haunt(rules, &mut rng);
+        return (state, rng);
+    }
+
+    #[test]
+    fn ghosts_start_away_from_the_chest_and_the_key() {
+        for seed in 0..20 {
+            let (state, _) = haunted(seed, 3);
+            assert_eq!(state.ghosts.len(), 3);
+            for ghost in state.ghosts.iter() {
+                assert!(ghost.room != 0 && ghost.room != state.key_index);
+                assert_eq!(ghost.room, ghost.home);
+            }
+        }
+    }
+
+    #[test]
+    fn no_ghosts_rolls_nothing() {
+        let (mut state, mut rng) = haunted(4, 0);
+        let mut untouched = rng.clone();
+        state.haunt(GhostRules::default(), &mut rng);
+        assert!(state.ghosts.is_empty());
+        assert_eq!(rng.gen::<u64>(), untouched.gen::<u64>());
+    }
+
+    #[test]
+    fn ghosts_roam_the_house_but_never_the_first_room() {
+        for seed in 0..20 {
+            let (mut state, mut rng) = haunted(seed, 1);
+            let mut rooms = vec![state.ghosts[0].room];
+            for _ in 0..20_000 {
+                assert!(!step(&mut state, &mut rng), "seed {}", seed);
+                let room = state.ghosts[0].room;
+                assert_ne!(room, 0);
+                if !rooms.contains(&room) {
+                    rooms.push(room);
+                }
+            }
+            //unless its home is a dead end off the first room
+            let home = state.ghosts[0].home;
+            let stuck = state.room_doors(home).all(|(_, door)| door.target == 0);
+            assert!(stuck || rooms.len() > 2, "seed {}: {:?}", seed, rooms);
+        }
+    }
+
+    #[test]
+    fn a_ghost_in_your_room_closes_in_until_it_reaches_you() {
+        let (mut state, mut rng) = haunted(2, 1);
+        state.current_room = state.ghosts[0].room;
+        state.player.pos = START_POS;
+        let mut gap = (state.ghosts[0].pos - state.player.pos).mag();
+        let mut ticks = 0;
+        while !step(&mut state, &mut rng) {
+            let closer = (state.ghosts[0].pos - state.player.pos).mag();
+            assert!(closer < gap);
+            gap = closer;
+            ticks += 1;
+        }
+        //half the player's speed covers the distance in the ticks it takes
+        let rules = state.ghost_rules;
+        let speed = state.tuning.speed * rules.speed;
+        assert_eq!(ticks, ((START_POS.mag() - rules.reach) / speed) as usize);
+    }
+
+    #[test]
+    fn ghosts_speed_up_with_every_house_cleared_up_to_a_cap() {
+        let (mut state, _) = haunted(1, 1);
+        let first = speed(&state);
+        state.cleared = 2;
+        assert!(speed(&state) > first);
+        state.cleared = 100;
+        assert_eq!(
+            speed(&state),
+            state.tuning.speed * state.ghost_rules.max_speed
+        );
+    }
+
+    #[test]
+    fn catches_parse_by_name() {
+        assert_eq!("end".parse(), Ok(Catch::End));
+        assert_eq!(Catch::Start.to_string(), "start");
+        assert!("eat".parse::<Catch>().is_err());
+    }
+}
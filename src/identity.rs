@@ -0,0 +1,154 @@
+use crate::geometry::corner_signs;
+use crate::types::*;
+use rand::Rng;
+use ultraviolet::Vec3;
+
+// What makes a room more than its wallpaper: the colour of the floor, the
+// landmark standing in the corner, and the name the two give it, such as
+// "Blue Study". The front end draws `floor_<colour>` and `landmark_<kind>`
+// models, so a new entry here needs both in the manifest.
+pub const FLOOR_COLOURS: [&str; 6] = ["Blue", "Red", "Green", "Yellow", "Grey", "White"];
+pub const LANDMARKS: [&str; 6] = [
+    "Study", "Parlour", "Library", "Pantry", "Gallery", "Nursery",
+];
+
+pub fn name(room: &Room) -> String {
+    return format!(
+        "{} {}",
+        FLOOR_COLOURS[room.floor_colour], LANDMARKS[room.landmark]
+    );
+}
+
+//manifest names of the models drawn for a room
+pub fn floor_model(colour: usize) -> String {
+    return format!("floor_{}", FLOOR_COLOURS[colour].to_lowercase());
+}
+
+pub fn landmark_model(landmark: usize) -> String {
+    return format!("landmark_{}", LANDMARKS[landmark].to_lowercase());
+}
+
+//how far out from the middle the landmark stands, as a share of the room, and
+//half its width, which is also how high its middle is off the floor
+pub const LANDMARK_CORNER: f32 = 0.3;
+pub const LANDMARK_SIZE: f32 = 5.0;
+
+//the middle of a room's landmark, in the corner across from the notch if there is one
+pub fn landmark_pos(shape: Shape, room_size: f32) -> Vec3 {
+    let step = room_size / STEPS as f32;
+    let (x, z) = match shape.notch {
+        Some(corner) => {
+            let (x, z) = corner_signs(corner);
+            (-x as f32, -z as f32)
+        }
+        None => (1.0, 1.0),
+    };
+    return Vec3::new(
+        x * shape.width as f32 * step * LANDMARK_CORNER,
+        LANDMARK_SIZE,
+        z * shape.depth as f32 * step * LANDMARK_CORNER,
+    );
+}
+
+//rooms a door leads to from roomidx, or that have a door leading into it
+pub fn neighbours(rooms: &[Room], doors: &[Door], roomidx: usize) -> Vec<usize> {
+    let mut found = vec![];
+    for (idx, room) in rooms.iter().enumerate() {
+        for dooridx in room.doors.iter() {
+            let target = doors[*dooridx].target;
+            let other = if idx == roomidx {
+                target
+            } else if target == roomidx {
+                idx
+            } else {
+                continue;
+            };
+            if other != roomidx && !found.contains(&other) {
+                found.push(other);
+            }
+        }
+    }
+    return found;
+}
+
+// Gives every room a floor colour and a landmark, room by room. Each one picks
+// from the pairs none of its neighbours has already taken, both by name and by
+// wallpaper and floor colour together. A room has at most four doors and there
+// are six floor colours, so on generated houses the fallbacks never run.
+pub fn assign<R: Rng>(rooms: &mut [Room], doors: &[Door], rng: &mut R) {
+    let mut done = vec![false; rooms.len()];
+    for roomidx in 0..rooms.len() {
+        let near: Vec<&Room> = neighbours(rooms, doors, roomidx)
+            .into_iter()
+            .filter(|idx| done[*idx])
+            .map(|idx| &rooms[idx])
+            .collect();
+        let tex_idx = rooms[roomidx].tex_idx;
+        let all: Vec<(usize, usize)> = (0..FLOOR_COLOURS.len())
+            .flat_map(|colour| (0..LANDMARKS.len()).map(move |landmark| (colour, landmark)))
+            .collect();
+        let new_name = |(colour, landmark): &(usize, usize)| {
+            near.iter()
+                .all(|other| (other.floor_colour, other.landmark) != (*colour, *landmark))
+        };
+        let new_look = |(colour, _): &(usize, usize)| {
+            near.iter()
+                .all(|other| (other.tex_idx, other.floor_colour) != (tex_idx, *colour))
+        };
+        let mut choices: Vec<(usize, usize)> = all
+            .iter()
+            .copied()
+            .filter(|pair| new_name(pair) && new_look(pair))
+            .collect();
+        if choices.is_empty() {
+            choices = all.iter().copied().filter(new_name).collect();
+        }
+        if choices.is_empty() {
+            choices = all;
+        }
+        let (colour, landmark) = choices[rng.gen_range(0..choices.len())];
+        rooms[roomidx].floor_colour = colour;
+        rooms[roomidx].landmark = landmark;
+        done[roomidx] = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Generator;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn neighbours_never_share_a_name_or_a_look() {
+        for generator in [Generator::Path, Generator::Tree] {
+            for seed in 0..30 {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let (rooms, doors) = generator.generate(12, 6, 2, &mut rng);
+                for (idx, room) in rooms.iter().enumerate() {
+                    for other in neighbours(&rooms, &doors, idx) {
+                        let other = &rooms[other];
+                        assert_ne!(name(room), name(other), "seed {}", seed);
+                        assert_ne!(
+                            (room.tex_idx, room.floor_colour),
+                            (other.tex_idx, other.floor_colour),
+                            "seed {}",
+                            seed
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn names_and_models_read_from_the_tables() {
+        let mut room = Room::new(vec![], 0);
+        room.floor_colour = 1;
+        room.landmark = 2;
+        assert_eq!(name(&room), "Red Library");
+        assert_eq!(floor_model(room.floor_colour), "floor_red");
+        assert_eq!(landmark_model(room.landmark), "landmark_library");
+    }
+}
@@ -1 +1,26 @@
-pub mod types;
\ No newline at end of file
+#![allow(clippy::needless_return)]
+pub mod assets;
+pub mod bot;
+pub mod collision;
+pub mod config;
+pub mod daily;
+pub mod geometry;
+pub mod ghost;
+pub mod identity;
+pub mod map;
+pub mod minimap;
+pub mod nav;
+pub mod options;
+pub mod progress;
+pub mod replay;
+pub mod rules;
+pub mod save;
+pub mod sim;
+pub mod state;
+pub mod text;
+pub mod times;
+pub mod transition;
+pub mod types;
+pub mod watch;
+
+pub const DT: f64 = 1.0 / 60.0;
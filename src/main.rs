@@ -1,7 +1,8 @@
-#![allow(dead_code)]
+#![allow(clippy::needless_return)]
+use chrono::NaiveDate;
 use frenderer::animation::{AnimationSettings, AnimationState};
 use frenderer::assets::AnimRef;
-//use frenderer::assets::TextureRef;
+use frenderer::assets::TextureRef;
 use frenderer::camera::{self, Camera};
 use frenderer::renderer::skinned::SingleRenderState as FSkinned;
 use frenderer::renderer::textured::SingleRenderState as FTextured;
@@ -11,36 +12,101 @@ use kira::instance::InstanceSettings;
 use kira::manager::{AudioManager, AudioManagerSettings};
 use kira::sound::handle::SoundHandle;
 use kira::sound::SoundSettings;
-use rand::Rng;
+use rand::rngs::StdRng;
+use scene3d::assets::{discover_glyphs, discover_themes, AssetError, Cache, Manifest, Registry};
+use scene3d::bot::Explorer;
+use scene3d::config::{Config, Tuning};
+use scene3d::daily::{self, History};
+use scene3d::geometry::*;
+use scene3d::identity;
+use scene3d::map::meets;
+use scene3d::minimap;
+use scene3d::options::Options;
+use scene3d::progress::Progress;
+use scene3d::replay::{Recorder, Recording, Retune, Snapshot};
+use scene3d::rules::{self, Controls, Event};
+use scene3d::save::SaveFile;
+use scene3d::sim::{Driver, Scripted};
+use scene3d::state::*;
+use scene3d::text;
+use scene3d::times::{BestTimes, Split};
+use scene3d::transition::Transition;
 use scene3d::types::*;
+use scene3d::watch::Watcher;
+use scene3d::DT;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
-const DT: f64 = 1.0 / 60.0;
-const SPEED: f32 = 0.5;
-const ROOMSIZE: f32 = 60.0;
-const COLLISION_RADIUS: f32 = 1.0;
-const SCALE: f32 = 10.0;
-const BUFFER: f32 = 5.0;
-const DOOR_WIDTH: f32 = 0.177;
-const DOOR_DEPTH: f32 = 0.07;
-const NUM_ROOMS: i32 = 4;
-const DIFFICULTY: usize = 3;
-const NUM_WALLPAPERS: usize = 4;
-
-#[derive(Clone)]
+//seconds of no input on the main screen before the bot starts playing
+const ATTRACT_DELAY: f64 = 10.0;
+//render ids from here up are the partition walls of the room on screen
+const WALL_IDS: usize = 20;
+//and from here up belong to the doors of the room on screen
+const DOOR_IDS: usize = 100;
+//and from here up to the rooms seen through them, forty ids per doorway
+const PORTAL_IDS: usize = 200;
+const PORTAL_STRIDE: usize = 40;
+//render ids from here up are the tiles of the transition curtain
+const CURTAIN_IDS: usize = 1000;
+//the curtain's grid, and how far in front of the camera it hangs
+const CURTAIN_COLS: usize = 16;
+const CURTAIN_ROWS: usize = 9;
+const CURTAIN_DISTANCE: f32 = 10.0;
+//text_plane.fbx is two units across; tiles overlap so no seams show
+const CURTAIN_TILE: f32 = 2.5;
+//and from here up to the glyphs of the HUD and the final screen's text
+const TEXT_IDS: usize = 2000;
+//glyph size, and how far in front of the camera text hangs, just before the curtain
+const TEXT_CELL: f32 = 0.7;
+const TEXT_DISTANCE: f32 = 9.5;
+//and from here up to the minimap's rooms, then its doors
+const MAP_IDS: usize = 3000;
+//middle of the minimap in view space, the most room it takes and its largest cell
+const MAP_CENTER: (f32, f32) = (9.0, 5.5);
+const MAP_SIZE: f32 = 6.0;
+const MAP_CELL: f32 = 1.2;
+//and from here up to the marks on doors, four per door
+const MARK_IDS: usize = 4000;
+//where the marks on a door go: off the wall, up the door, and how big
+const MARK_OUT: f32 = 0.3;
+const MARK_HEIGHT: f32 = 12.0;
+const MARK_GAP: f32 = 2.5;
+const MARK_SIZE: f32 = 1.0;
+//and from here up to the blocks of staircases, two abreast, STAIR_STEPS deep
+const STAIR_IDS: usize = 5000;
+const STAIR_STEPS: usize = 8;
+//how tall and deep a step is, and how far the camera climbs going up a flight
+const STAIR_STEP: f32 = 5.0;
+const STAIR_CLIMB: f32 = 20.0;
+//and from here up to the ghosts in the room, how high they float and how big they are
+const GHOST_IDS: usize = 6000;
+const GHOST_HEIGHT: f32 = 9.0;
+const GHOST_SIZE: f32 = 5.0;
+//seconds a room's name stays up after walking in
+const NAME_TIME: f64 = 2.0;
+//how much of the floor the covering takes
+const FLOOR_SHARE: f32 = 0.3;
+//seconds between looks at the disk in --dev
+const RELOAD_INTERVAL: f64 = 0.5;
+
+//puts a freshly loaded texture on a mesh loaded at startup
+type Repaint = Rc<
+    dyn Fn(&mut frenderer::assets::Assets, TextureRef) -> Rc<frenderer::renderer::textured::Model>,
+>;
+
+//what a texture is drawn on, so --dev can paint the same mesh with a new copy
+enum Painted {
+    Model(String), //by its name in the manifest
+    Room(usize),   //a theme's room, by index into the themes
+    Door(usize),   //a theme's own door
+    Glyph(char),
+}
 
-pub struct GameState {
-    pub current_room: usize, //index of room in rooms
-    pub max_rooms: usize,
-    pub key_index: usize,
-    pub rooms: Vec<Room>,
-    pub doors: Vec<Door>,
-    pub is_finished: bool,
-    pub has_key: bool,
-    pub gameplaystate: GameplayState,
-    pub audio_play: bool,
-    pub has_rotated: bool,
-    pub wallpapers: Vec<std::rc::Rc<frenderer::renderer::textured::Model>>,
+struct Paint {
+    texture: PathBuf,
+    on: Painted,
+    repaint: Repaint,
 }
 
 #[derive(Clone)]
@@ -69,448 +135,1163 @@ impl GameObject {
         self.state.tick(DT);
         //dbg!(self.state);
     }
+}
 
-    pub fn move_by(&mut self, vec: Vec3) {
-        if self.trf.translation.x + vec.x < ROOMSIZE / 2.0
-            && self.trf.translation.x + vec.x > -ROOMSIZE / 2.0
-            && self.trf.translation.z + vec.z < ROOMSIZE / 2.0
-            && self.trf.translation.z + vec.z > -ROOMSIZE / 2.0
-        {
-            self.trf.append_translation(vec);
-        }
-    }
+struct World {
+    camera: Camera,
+    audio: Vec<SoundHandle>,
+    things: Vec<GameObject>,
+    main_screen_textured: Vec<Textured>,
+    textured: Vec<Textured>,
+    doors: Vec<DoorInstance>, //the doors of the room on screen
+    door_models: DoorModels,
+    marks: DoorMarks,
+    looks: RoomLooks,
+    partition: Rc<frenderer::renderer::textured::Model>, //the walls of narrow rooms and Ls
+    stairs: Rc<frenderer::renderer::textured::Model>,    //one block of a staircase
+    ghost: Rc<frenderer::renderer::textured::Model>,
+    entered: f64, //seconds since the current room came into view, for its name
+    passage: Option<Passage>, //a door swinging as the player walks through it
+    screen_change: Option<ScreenChange>,
+    curtain: Rc<frenderer::renderer::textured::Model>,
+    glyphs: HashMap<char, Rc<frenderer::renderer::textured::Model>>,
+    seed: u64,       //the map seed, shown so a run can be played again
+    seeded: bool,    //the house is the one seed builds, not one restarted into after it
+    play_ticks: u64, //ticks spent in this house
+    best_times: BestTimes,
+    times_path: PathBuf,
+    split: Option<Split>,     //the last time attack against the bests before it
+    daily: Option<NaiveDate>, //the day of the daily house being played, until it is opened
+    history: History,
+    history_path: PathBuf,
+    summary: Option<Vec<String>>, //how the daily house went, for the final screen
+    progress: Progress,
+    progress_path: PathBuf,
+    map: Minimap,
+    themes: Vec<RoomTheme>,
+    audio_play: bool,
+    has_rotated: bool,
+    demo: Option<Demo>,
+    idle: f64, //seconds on the main screen without a key press
+    rng: StdRng,
+    recorder: Recorder,
+    record_path: PathBuf,
+    replay: Option<Replay>,
+    config: Config,
+    dev: Option<DevReload>,
+    state: GameState,
+}
 
-    pub fn get_dir(&mut self) -> Direction {
-        if self.trf.rotation == Rotor3::from_euler_angles(0.0, 0.0, 0.0) {
-            return Direction::South;
-        } else if self.trf.rotation == Rotor3::from_euler_angles(0.0, 0.0, PI / 2.0 as f32) {
-            return Direction::West;
-        } else if self.trf.rotation == Rotor3::from_euler_angles(0.0, 0.0, PI as f32) {
-            return Direction::North;
-        } else {
-            return Direction::East;
-        }
-    }
+//--dev: watches the files the game loaded and swaps changes in while it runs
+struct DevReload {
+    watcher: Watcher,
+    config_path: PathBuf,
+    models: Registry<Rc<frenderer::renderer::textured::Model>>,
+    paints: Vec<Paint>,
+    theme_dir: PathBuf,
+    font: Option<Font>,
+    timer: f64,
 }
 
-struct Sprite {
-    trf: Isometry3,
-    tex: frenderer::assets::TextureRef,
-    cel: Rect,
-    size: Vec2,
-    tex_model: Textured,
+//where glyphs come from, so ones added while the game runs can be drawn too
+struct Font {
+    dir: PathBuf,
+    repaint: Repaint,
 }
-impl Sprite {
-    pub fn move_by(&mut self, vec: Vec3) {
-        self.trf.append_translation(vec);
-        self.tex_model.trf.append_translation(vec);
-    }
 
-    pub fn check_item_collisions(
-        &mut self,
-        direction: Direction,
-        obj_edge_length_x: f32,
-        obj_edge_length_z: f32,
-        object: &Textured,
-    ) -> bool {
-        if direction == Direction::North || direction == Direction::South {
-            return self.trf.translation.x <= object.trf.translation.x + obj_edge_length_x
-                && self.trf.translation.x >= object.trf.translation.x - obj_edge_length_x
-                && self.trf.translation.z <= object.trf.translation.z + obj_edge_length_z
-                && self.trf.translation.z >= object.trf.translation.z - obj_edge_length_z;
-        } else if direction == Direction::East || direction == Direction::West {
-            return self.trf.translation.z <= object.trf.translation.z + obj_edge_length_z
-                && self.trf.translation.z >= object.trf.translation.z - obj_edge_length_z
-                && self.trf.translation.x >= object.trf.translation.x - obj_edge_length_x
-                && self.trf.translation.x <= object.trf.translation.x + obj_edge_length_x;
-        } else {
-            return false;
-        }
-    }
+// Walking through a door: the old room while its door swings open and the
+// camera leans in, then the new room while the door behind swings shut. The
+// simulation has already moved on; this only changes what is drawn.
+struct Passage {
+    before: GameState, //the room being left, as it was at the door
+    door: usize,       //walked through, in before
+    back: usize,       //come out of, in the new room
+    transition: Transition,
+}
 
-    pub fn check_collisions(&mut self, door: Door) -> bool {
-        let door_worldspace = get_trf(door.direction, ROOMSIZE, SCALE);
-        if door.direction == Direction::North {
-            return self.trf.translation.z + BUFFER / 2.0
-                >= door_worldspace.translation.z - DOOR_DEPTH * SCALE
-                && self.trf.translation.x
-                    <= door_worldspace.translation.x + DOOR_WIDTH * SCALE * 3.0
-                && self.trf.translation.x
-                    >= door_worldspace.translation.x - DOOR_WIDTH * SCALE * 3.0;
-        } else if door.direction == Direction::South {
-            return self.trf.translation.z - BUFFER / 2.0
-                <= door_worldspace.translation.z + DOOR_DEPTH * SCALE
-                && self.trf.translation.x
-                    <= door_worldspace.translation.x + DOOR_WIDTH * SCALE * 3.0
-                && self.trf.translation.x
-                    >= door_worldspace.translation.x - DOOR_WIDTH * SCALE * 3.0;
-        } else if door.direction == Direction::East {
-            return self.trf.translation.x + BUFFER / 2.0
-                >= door_worldspace.translation.x - DOOR_DEPTH * SCALE
-                && self.trf.translation.z
-                    <= door_worldspace.translation.z + DOOR_WIDTH * SCALE * 3.0
-                && self.trf.translation.z
-                    >= door_worldspace.translation.z - DOOR_WIDTH * SCALE * 3.0;
-        } else if door.direction == Direction::West {
-            return self.trf.translation.x - BUFFER / 2.0
-                <= door_worldspace.translation.x + DOOR_DEPTH * SCALE
-                && self.trf.translation.z
-                    <= door_worldspace.translation.z + DOOR_WIDTH * SCALE * 3.0
-                && self.trf.translation.z
-                    >= door_worldspace.translation.z - DOOR_WIDTH * SCALE * 3.0;
-        } else {
-            return false;
+impl Passage {
+    //which door is swinging and how far open it is; a cross-fade can show either
+    //room at any point, so each door stays wide open outside its own half
+    fn swing(&self) -> (usize, f32) {
+        let progress = self.transition.progress();
+        if !self.transition.shows_new() {
+            return (self.door, (progress * 2.0).min(1.0));
         }
+        return (self.back, (2.0 - progress * 2.0).min(1.0));
     }
 }
 
-#[derive(Clone, PartialEq)]
-pub enum GameplayState {
-    Mainscreen,
-    Instructions,
-    Play,
-    FinalScreen,
+//a GameplayState change being drawn; from is still on screen until halfway
+struct ScreenChange {
+    from: GameplayState,
+    transition: Transition,
 }
-struct World {
-    camera: Camera,
-    audio: Vec<SoundHandle>,
-    things: Vec<GameObject>,
-    sprites: Vec<Sprite>,
-    main_screen_textured: Vec<Textured>,
-    textured: Vec<Textured>,
-    door1: Textured,
-    door2: Textured,
-    door3: Textured,
-    door4: Textured,
-    room: Textured,
-    door_collider: Vec2,
+
+//a recording being fed back in place of the keyboard
+struct Replay {
+    recording: Recording,
+    inputs: Scripted,
+}
+
+//attract mode: a house the bot plays while nobody is at the keyboard
+struct Demo {
     state: GameState,
+    bot: Explorer,
+}
+//the room mesh and door model painted in one theme
+struct RoomTheme {
+    room: Rc<frenderer::renderer::textured::Model>,
+    door: Rc<frenderer::renderer::textured::Model>,
+    door_texture: Option<PathBuf>, //None draws the manifest's door
 }
-struct Flat {
+//where the rooms of the current house sit on the minimap, worked out once per house
+struct Minimap {
+    layout: Vec<Vec2>,
+    room: Rc<frenderer::renderer::textured::Model>,
+    here: Rc<frenderer::renderer::textured::Model>,
+    door: Rc<frenderer::renderer::textured::Model>,
+}
+
+//one door of the room on screen
+struct DoorInstance {
+    door: usize, //index into GameState::doors
     trf: Similarity3,
-    model: Rc<frenderer::renderer::flat::Model>,
+    look: DoorLook,
+    swing: f32, //how far open it is drawn mid-swing, 0 when it is at rest
+}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum DoorLook {
+    Closed,
+    Open,
+    Locked,
+}
+//breadcrumbs on doors already taken, up to three, or one dead end mark instead
+struct DoorMarks {
+    breadcrumb: Rc<frenderer::renderer::textured::Model>,
+    dead_end: Rc<frenderer::renderer::textured::Model>,
+}
+
+//what identity::FLOOR_COLOURS and identity::LANDMARKS look like, in their order
+struct RoomLooks {
+    floor_colours: Vec<Rc<frenderer::renderer::textured::Model>>,
+    landmarks: Vec<Rc<frenderer::renderer::textured::Model>>,
+}
+
+impl RoomLooks {
+    fn look_up(
+        models: &Registry<Rc<frenderer::renderer::textured::Model>>,
+    ) -> std::result::Result<RoomLooks, AssetError> {
+        return Ok(RoomLooks {
+            floor_colours: (0..identity::FLOOR_COLOURS.len())
+                .map(|colour| models.get(&identity::floor_model(colour)))
+                .collect::<std::result::Result<_, _>>()?,
+            landmarks: (0..identity::LANDMARKS.len())
+                .map(|landmark| models.get(&identity::landmark_model(landmark)))
+                .collect::<std::result::Result<_, _>>()?,
+        });
+    }
+}
+
+//variants shared by every theme; closed doors use the theme's own door, and
+//only door_locked ships, so open doors are usually that door swung wide
+struct DoorModels {
+    open: Option<Rc<frenderer::renderer::textured::Model>>,
+    locked: Option<Rc<frenderer::renderer::textured::Model>>,
 }
 struct Textured {
     trf: Similarity3,
     model: Rc<frenderer::renderer::textured::Model>,
-    name: String,
 }
+impl World {
+    //the room the player is standing in, with everything in it
+    fn render_house(&self, state: &GameState, rs: &mut frenderer::renderer::RenderState) {
+        //render the key if we are in the right room and if we don't have the key
+        if state.key_index == state.current_room && !state.has_key {
+            //render the key
+            rs.render_textured(
+                10,
+                self.textured[1].model.clone(),
+                FTextured::new(self.textured[1].trf),
+            );
+        }
+        //render the chest in the starting room
+        else if state.current_room == 0 {
+            //render the block
+            // rs.render_textured(
+            //     9,
+            //     self.textured[0].model.clone(),
+            //     FTextured::new(self.textured[0].trf),
+            // );
 
-impl Textured {
-    pub fn move_by(&mut self, vec: Vec3) {
-        self.trf.append_translation(vec);
-    }
-}
-impl frenderer::World for World {
-    fn update(&mut self, input: &frenderer::Input, _assets: &mut frenderer::assets::Assets) {
-        //currently WAS
+            rs.render_textured(
+                10,
+                self.textured[2].model.clone(),
+                FTextured::new(self.textured[2].trf),
+            );
+        }
 
-        if self.state.audio_play {
-            self.audio[0].play(InstanceSettings::default());
-            self.state.audio_play = false;
+        //place doors
+        let theme = self.theme(state.rooms[state.current_room].tex_idx);
+        for (i, door) in self.doors.iter().enumerate() {
+            let (model, trf) = match door.look {
+                //a swinging door is the plain door whatever it looks like at rest
+                _ if door.swing > 0.0 => (theme.door.clone(), door.trf),
+                DoorLook::Closed => (theme.door.clone(), door.trf),
+                //an open door with no model of its own is the plain door swung wide
+                DoorLook::Open => match &self.door_models.open {
+                    Some(model) => (model.clone(), door.trf),
+                    None => {
+                        let dir = state.doors[door.door].direction;
+                        let reach = state.tuning.door_reach();
+                        (theme.door.clone(), swing_door(door.trf, dir, reach, 1.0))
+                    }
+                },
+                DoorLook::Locked => match &self.door_models.locked {
+                    Some(model) => (model.clone(), door.trf),
+                    None => (theme.door.clone(), door.trf),
+                },
+            };
+            rs.render_textured(DOOR_IDS + i, model, FTextured::new(trf));
+        }
+        self.render_marks(state, rs);
+        self.render_stairs(state, rs);
+        self.render_ghosts(state, rs);
+
+        //render the game object
+        rs.render_skinned(
+            5,
+            self.things[0].model.clone(),
+            FSkinned::new(
+                self.things[0].animation,
+                self.things[0].state,
+                self.things[0].trf,
+            ),
+        );
+
+        rs.render_skinned(
+            7,
+            self.things[0].model.clone(),
+            FSkinned::new(
+                self.things[0].animation,
+                self.things[0].state,
+                self.things[0].trf,
+            ),
+        );
+
+        //render room
+        rs.render_textured(
+            6,
+            theme.room.clone(),
+            FTextured::new(Similarity3::new(
+                Vec3::new(0.0, state.tuning.room_size / 2., 0.0),
+                Rotor3::from_euler_angles(0.0, 0.0, 0.0),
+                state.tuning.room_size / 2.,
+            )),
+        );
+        let room = &state.rooms[state.current_room];
+        self.render_partitions(
+            room.shape,
+            Vec3::zero(),
+            state.tuning.room_size,
+            WALL_IDS,
+            rs,
+        );
+        self.render_looks(room, Vec3::zero(), state.tuning.room_size, 11, rs);
+
+        //rooms next door show through open doorways that line up with the door back
+        for (i, instance) in self.doors.iter().enumerate() {
+            let door = state.doors[instance.door];
+            let open = instance.look == DoorLook::Open || instance.swing > 0.0;
+            if open && meets(&state.rooms, &state.doors, state.current_room, door) {
+                self.render_neighbour(state, door, PORTAL_IDS + i * PORTAL_STRIDE, rs);
+            }
         }
+    }
+
+    //houses painted in more themes than are installed, like the daily house's, wrap round them
+    fn theme(&self, tex_idx: usize) -> &RoomTheme {
+        return &self.themes[tex_idx % self.themes.len()];
+    }
+
+    fn screen_transition(&self) -> Transition {
+        let transitions = self.config.transitions;
+        return Transition::new(transitions.screen, transitions.screen_time);
+    }
 
-        // let yaw = input.key_axis(Key::Q, Key::W) * PI / 4.0 * DT as f32;
-        // let pitch = input.key_axis(Key::A, Key::S) * PI / 4.0 * DT as f32;
-        // let roll = input.key_axis(Key::Z, Key::X) * PI / 4.0 * DT as f32;
-        // let dscale = input.key_axis(Key::E, Key::R) * 1.0 * DT as f32;
-        // let rot = Rotor3::from_euler_angles(roll, pitch, yaw);
-
-        //Press S to play
-        if self.state.gameplaystate == GameplayState::Mainscreen {
-            if input.is_key_down(Key::S) {
-                //self.textured[0].trf.append_translation(to_the_moon);
-                self.state.gameplaystate = GameplayState::Instructions;
+    //black tiles hung just in front of the camera, for whichever transition is running
+    fn render_curtain(&self, camera: &Camera, rs: &mut frenderer::renderer::RenderState) {
+        let mut covered = [false; CURTAIN_COLS * CURTAIN_ROWS];
+        let running = [
+            self.passage.as_ref().map(|passage| passage.transition),
+            self.screen_change.as_ref().map(|change| change.transition),
+        ];
+        for transition in running.iter().flatten() {
+            let tiles = transition.curtain(CURTAIN_COLS, CURTAIN_ROWS);
+            for (cover, tile) in covered.iter_mut().zip(tiles) {
+                *cover |= tile;
             }
         }
+        for (i, _) in covered.iter().enumerate().filter(|(_, cover)| **cover) {
+            let col = (i % CURTAIN_COLS) as f32 - (CURTAIN_COLS as f32 - 1.0) / 2.0;
+            let row = (i / CURTAIN_COLS) as f32 - (CURTAIN_ROWS as f32 - 1.0) / 2.0;
+            let pos = Vec3::new(col * CURTAIN_TILE, -row * CURTAIN_TILE, -CURTAIN_DISTANCE);
+            let trf = in_front(camera, pos, CURTAIN_TILE * 0.6);
+            rs.render_textured(CURTAIN_IDS + i, self.curtain.clone(), FTextured::new(trf));
+        }
+    }
+
+    // Lines of text facing the camera, top left corner at corner in view
+    // space. Characters the font lacks are tried in uppercase, then skipped.
+    fn render_text(
+        &self,
+        camera: &Camera,
+        lines: &[String],
+        corner: Vec2,
+        ids: usize,
+        rs: &mut frenderer::renderer::RenderState,
+    ) {
+        for (i, placed) in text::layout(lines).iter().enumerate() {
+            let glyph = self
+                .glyphs
+                .get(&placed.ch)
+                .or_else(|| self.glyphs.get(&placed.ch.to_ascii_uppercase()));
+            let glyph = match glyph {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            let pos = Vec3::new(
+                corner.x + (placed.col as f32 + 0.5) * TEXT_CELL,
+                corner.y - (placed.row as f32 + 0.5) * TEXT_CELL,
+                -TEXT_DISTANCE,
+            );
+            let trf = in_front(camera, pos, TEXT_CELL / 2.0);
+            rs.render_textured(ids + i, glyph.clone(), FTextured::new(trf));
+        }
+    }
 
-        //Press P to play
-        if self.state.gameplaystate == GameplayState::Instructions {
-            if input.is_key_down(Key::P) {
-                //self.textured[0].trf.append_translation(to_the_moon);
-                self.state.gameplaystate = GameplayState::Play;
+    //the rooms visited so far and the doors between them, scaled to fit the corner
+    fn render_minimap(&self, camera: &Camera, rs: &mut frenderer::renderer::RenderState) {
+        let state = &self.state;
+        //only the storey the player is on
+        let storey = state.rooms[state.current_room].storey;
+        let shown = |room: usize| state.visited[room] && state.rooms[room].storey == storey;
+        let seen: Vec<Vec2> = (0..state.rooms.len())
+            .filter(|room| shown(*room))
+            .map(|room| self.map.layout[room])
+            .collect();
+        let (mut low, mut high) = (seen[0], seen[0]);
+        for at in seen.iter() {
+            low = low.min_by_component(*at);
+            high = high.max_by_component(*at);
+        }
+        let extent = (high - low).component_max() + 1.0;
+        let cell = MAP_CELL.min(MAP_SIZE / extent);
+        let middle = (low + high) / 2.0;
+        //the map's y is the room's z, which points down the screen
+        let place = |at: Vec2| {
+            let at = (at - middle) * cell;
+            return Vec3::new(MAP_CENTER.0 + at.x, MAP_CENTER.1 - at.y, -TEXT_DISTANCE);
+        };
+        for (room, at) in self.map.layout.iter().enumerate() {
+            if !shown(room) {
+                continue;
             }
+            let model = if room == state.current_room {
+                self.map.here.clone()
+            } else {
+                self.map.room.clone()
+            };
+            let trf = in_front(camera, place(*at), cell * 0.4);
+            rs.render_textured(MAP_IDS + room, model, FTextured::new(trf));
         }
-        //controls for gameplaystate play
-        else if self.state.gameplaystate == GameplayState::Play {
-            if !self.state.has_rotated {
-                self.camera
-                    .transform
-                    .prepend_rotation(Rotor3::from_rotation_xz(PI / 4.0));
-                self.state.has_rotated = true;
+        for (i, (a, b)) in minimap::explored_doors(state).into_iter().enumerate() {
+            //stairs show as a door off the edge of this storey's rooms
+            if !shown(a) && !shown(b) {
+                continue;
             }
+            let at = (self.map.layout[a] + self.map.layout[b]) / 2.0;
+            let trf = in_front(camera, place(at), cell * 0.12);
+            rs.render_textured(
+                MAP_IDS + state.rooms.len() + i,
+                self.map.door.clone(),
+                FTextured::new(trf),
+            );
+        }
+    }
 
-            for s in self.sprites.iter_mut() {
-                if s.check_item_collisions(self.things[0].get_dir(), 7.75, 7.75, &self.textured[0])
-                    && self.state.current_room == 0
-                {
-                    // check if collide with item and spawn back
-                    // dbg!(self.things[0].get_dir());
-                    if self.things[0].get_dir() == Direction::North {
-                        s.trf.translation += Vec3::new(0.0, 0.0, BUFFER / 2.0);
-                        s.tex_model.trf.translation += Vec3::new(0.0, 0.0, BUFFER / 2.0);
-                        self.things[0].trf.translation += Vec3::new(0.0, 0.0, BUFFER / 2.0);
-                    } else if self.things[0].get_dir() == Direction::South {
-                        s.trf.translation += Vec3::new(0.0, 0.0, -BUFFER / 2.0);
-                        s.tex_model.trf.translation += Vec3::new(0.0, 0.0, -BUFFER / 2.0);
-                        self.things[0].trf.translation += Vec3::new(0.0, 0.0, -BUFFER / 2.0);
-                    } else if self.things[0].get_dir() == Direction::East {
-                        s.trf.translation += Vec3::new(-BUFFER / 2.0, 0.0, 0.0);
-                        s.tex_model.trf.translation += Vec3::new(-BUFFER / 2.0, 0.0, 0.0);
-                        self.things[0].trf.translation += Vec3::new(-BUFFER / 2.0, 0.0, 0.0);
-                    } else {
-                        s.trf.translation += Vec3::new(BUFFER / 2.0, 0.0, 0.0);
-                        s.tex_model.trf.translation += Vec3::new(BUFFER / 2.0, 0.0, 0.0);
-                        self.things[0].trf.translation += Vec3::new(BUFFER / 2.0, 0.0, 0.0);
-                    }
+    fn render_marks(&self, state: &GameState, rs: &mut frenderer::renderer::RenderState) {
+        for (i, instance) in self.doors.iter().enumerate() {
+            let door = state.doors[instance.door];
+            let (model, count) =
+                if self.config.hints.mark_dead_ends && state.explored_dead_end(instance.door) {
+                    (&self.marks.dead_end, 1)
                 } else {
-                    if input.is_key_down(Key::W)
-                        || input.is_key_down(Key::A)
-                        || input.is_key_down(Key::S)
-                        || input.is_key_down(Key::D)
-                    {
-                        self.things[0].tick_animation();
-                    }
-                    if input.is_key_down(Key::W) {
-                        s.move_by(Vec3::new(0.0, 0.0, -SPEED));
-                        if self.things[0].get_dir() != Direction::North {
-                            self.things[0].trf.rotation =
-                                Rotor3::from_euler_angles(0.0, 0.0, PI as f32);
-                        }
-                        self.things[0].move_by(Vec3::new(0.0, 0.0, -SPEED));
-                    }
-                    if input.is_key_down(Key::S) {
-                        s.move_by(Vec3::new(0.0, 0.0, SPEED));
-                        if self.things[0].get_dir() != Direction::South {
-                            self.things[0].trf.rotation = Rotor3::from_euler_angles(0.0, 0.0, 0.0);
-                        }
-                        self.things[0].move_by(Vec3::new(0.0, 0.0, SPEED));
+                    (&self.marks.breadcrumb, door.uses.min(3) as usize)
+                };
+            let shape = state.rooms[state.current_room].shape;
+            for slot in 0..count {
+                let trf = mark_trf(shape, door, &state.tuning, slot);
+                rs.render_textured(MARK_IDS + i * 4 + slot, model.clone(), FTextured::new(trf));
+            }
+        }
+    }
+
+    // A flight of blocks behind each staircase's door, seen through it when it
+    // opens: climbing away from the wall for stairs up, and going down below
+    // the floor for stairs down, the top step level with it.
+    fn render_stairs(&self, state: &GameState, rs: &mut frenderer::renderer::RenderState) {
+        let shape = state.rooms[state.current_room].shape;
+        for (i, instance) in self.doors.iter().enumerate() {
+            let door = state.doors[instance.door];
+            if door.stairs.is_none() {
+                continue;
+            }
+            let out = -get_inward(door.direction);
+            let along = Vec3::new(out.z, 0.0, out.x);
+            let foot = door_pos(shape, door, state.tuning.room_size);
+            for step in 0..STAIR_STEPS {
+                let depth = (step as f32 + 0.5) * STAIR_STEP;
+                let height = if climb(door) > 0.0 { depth } else { -depth };
+                for (side, sign) in [-0.5, 0.5].iter().enumerate() {
+                    let at = foot
+                        + out * depth
+                        + along * (sign * STAIR_STEP)
+                        + Vec3::new(0.0, height, 0.0);
+                    let trf = Similarity3::new(at, Rotor3::identity(), STAIR_STEP / 2.0);
+                    let id = STAIR_IDS + (i * STAIR_STEPS + step) * 2 + side;
+                    rs.render_textured(id, self.stairs.clone(), FTextured::new(trf));
+                }
+            }
+        }
+    }
+
+    //the ghosts in the player's room, each turned to look at them
+    fn render_ghosts(&self, state: &GameState, rs: &mut frenderer::renderer::RenderState) {
+        for (i, ghost) in state.ghosts.iter().enumerate() {
+            if ghost.room != state.current_room {
+                continue;
+            }
+            let mut look = state.player.pos - ghost.pos;
+            look.y = 0.0;
+            let turn = if look.mag() > 1e-3 {
+                face(look.normalized())
+            } else {
+                Rotor3::identity()
+            };
+            let at = ghost.pos + Vec3::new(0.0, GHOST_HEIGHT, 0.0);
+            let trf = Similarity3::new(at, turn, GHOST_SIZE);
+            rs.render_textured(GHOST_IDS + i, self.ghost.clone(), FTextured::new(trf));
+        }
+    }
+
+    //the room behind door, moved over by one room so it lines up with this one
+    fn render_neighbour(
+        &self,
+        state: &GameState,
+        door: Door,
+        ids: usize,
+        rs: &mut frenderer::renderer::RenderState,
+    ) {
+        let size = state.tuning.room_size;
+        let cell = get_offset(door.direction);
+        let offset = Vec3::new(cell.x as f32 * size, 0.0, cell.y as f32 * size);
+        let next = &state.rooms[door.target];
+        let theme = self.theme(next.tex_idx);
+        rs.render_textured(
+            ids,
+            theme.room.clone(),
+            FTextured::new(Similarity3::new(
+                offset + Vec3::new(0.0, size / 2., 0.0),
+                Rotor3::identity(),
+                size / 2.,
+            )),
+        );
+        for (i, (_, back)) in state.room_doors(door.target).enumerate() {
+            //the way back is the doorway we are looking through
+            if back.target == state.current_room {
+                continue;
+            }
+            let mut trf = door_trf(next.shape, back, &state.tuning);
+            trf.translation += offset;
+            rs.render_textured(ids + 1 + i, theme.door.clone(), FTextured::new(trf));
+        }
+        self.render_looks(next, offset, size, ids + 5, rs);
+        self.render_partitions(next.shape, offset, size, ids + 7, rs);
+    }
+
+    // The walls of a shape that stand in from the edge of its cell, where the
+    // room mesh has none, as square tiles facing into the room. Each stretch is
+    // stacked floor to ceiling with tiles as wide as it is long.
+    fn render_partitions(
+        &self,
+        shape: Shape,
+        offset: Vec3,
+        size: f32,
+        ids: usize,
+        rs: &mut frenderer::renderer::RenderState,
+    ) {
+        let step = size / STEPS as f32;
+        let mut id = ids;
+        for wall in walls(shape) {
+            if wall.across * 2 == STEPS {
+                continue;
+            }
+            let side = (wall.to - wall.from) as f32 * step;
+            let middle = (wall.from + wall.to) as f32 * step / 2.0;
+            let out = get_offset(wall.direction);
+            let across = wall.across as f32 * step;
+            let base = if out.x == 0 {
+                Vec3::new(middle, 0.0, out.y as f32 * across)
+            } else {
+                Vec3::new(out.x as f32 * across, 0.0, middle)
+            };
+            let rotation = face(get_inward(wall.direction));
+            let rows = (size / side).ceil() as usize;
+            for row in 0..rows {
+                //the top row overlaps the one below rather than poke through the ceiling
+                let height = (side / 2.0 + row as f32 * side).min(size - side / 2.0);
+                let trf = Similarity3::new(
+                    offset + base + Vec3::new(0.0, height, 0.0),
+                    rotation,
+                    side / 2.0,
+                );
+                rs.render_textured(id, self.partition.clone(), FTextured::new(trf));
+                id += 1;
+            }
+        }
+    }
+
+    //a room's floor covering and its landmark in the far corner, two ids from ids
+    fn render_looks(
+        &self,
+        room: &Room,
+        offset: Vec3,
+        size: f32,
+        ids: usize,
+        rs: &mut frenderer::renderer::RenderState,
+    ) {
+        let step = size / STEPS as f32;
+        let (width, depth) = (
+            room.shape.width as f32 * step,
+            room.shape.depth as f32 * step,
+        );
+        //the covering goes in the middle of the biggest box the floor holds
+        let (x, z) = room.shape.notch.map_or((0, 0), corner_signs);
+        let (middle, short) = match room.shape.notch {
+            None => (Vec3::zero(), width.min(depth)),
+            Some(_) if width >= depth => (
+                Vec3::new(0.0, 0.0, -z as f32 * depth / 4.0),
+                width.min(depth / 2.0),
+            ),
+            Some(_) => (
+                Vec3::new(-x as f32 * width / 4.0, 0.0, 0.0),
+                (width / 2.0).min(depth),
+            ),
+        };
+        //flat models face +z, so tip it over to face the ceiling
+        let flat = Rotor3::from_rotation_between(Vec3::unit_z(), Vec3::unit_y());
+        let floor = Similarity3::new(
+            offset + middle + Vec3::new(0.0, 0.1, 0.0),
+            flat,
+            short * FLOOR_SHARE,
+        );
+        rs.render_textured(
+            ids,
+            self.looks.floor_colours[room.floor_colour].clone(),
+            FTextured::new(floor),
+        );
+        let landmark = Similarity3::new(
+            offset + identity::landmark_pos(room.shape, size),
+            Rotor3::identity(),
+            identity::LANDMARK_SIZE,
+        );
+        rs.render_textured(
+            ids + 1,
+            self.looks.landmarks[room.landmark].clone(),
+            FTextured::new(landmark),
+        );
+    }
+
+    fn hot_reload(&mut self, assets: &mut frenderer::assets::Assets) {
+        let mut dev = match self.dev.take() {
+            Some(dev) => dev,
+            None => return,
+        };
+        dev.timer += DT;
+        if dev.timer >= RELOAD_INTERVAL {
+            dev.timer = 0.0;
+            for path in dev.watcher.poll() {
+                self.reload(&mut dev, &path, assets);
+            }
+        }
+        self.dev = Some(dev);
+    }
+
+    fn reload(&mut self, dev: &mut DevReload, path: &Path, assets: &mut frenderer::assets::Assets) {
+        if path == dev.config_path {
+            //house settings take effect on the next restart
+            match Config::load(path) {
+                Ok(config) => {
+                    self.config = config;
+                    //kept in the recording too, or it would replay with the old values
+                    let retune = Retune {
+                        tuning: config.tuning,
+                        floors: config.house.floors,
+                        difficulty: config.house.difficulty,
+                        generator: config.house.generator,
+                        ghosts: config.ghosts,
+                        levels: self.state.levels.map(|_| config.levels),
+                    };
+                    self.recorder.retune(retune);
+                    retune.apply(&mut self.state);
+                    eprintln!("reloaded {}", path.display());
+                }
+                Err(err) => eprintln!("{}: {}\nkeeping the old values", path.display(), err),
+            }
+            return;
+        }
+        //a new theme changes which theme each room of a seed gets
+        if path == dev.theme_dir {
+            let found = discover_themes(path).map(|themes| themes.len());
+            if found.map_or(true, |count| count != self.themes.len()) {
+                eprintln!(
+                    "{} changed; restart the game to paint rooms in it",
+                    path.display()
+                );
+            }
+            return;
+        }
+        if let Some(font) = &dev.font {
+            if path == font.dir {
+                let glyphs = match discover_glyphs(path) {
+                    Ok(glyphs) => glyphs,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        return;
                     }
-                    if input.is_key_down(Key::A) {
-                        s.move_by(Vec3::new(-SPEED, 0.0, 0.0));
-                        if self.things[0].get_dir() != Direction::West {
-                            self.things[0].trf.rotation =
-                                Rotor3::from_euler_angles(0.0, 0.0, PI / 2.0 as f32);
-                        }
-                        self.things[0].move_by(Vec3::new(-SPEED, 0.0, 0.0));
+                };
+                for (ch, file) in glyphs {
+                    if self.glyphs.contains_key(&ch) {
+                        continue;
                     }
-                    if input.is_key_down(Key::D) {
-                        s.move_by(Vec3::new(SPEED, 0.0, 0.0));
-                        if self.things[0].get_dir() != Direction::East {
-                            self.things[0].trf.rotation =
-                                Rotor3::from_euler_angles(0.0, 0.0, -PI / 2.0 as f32);
+                    match assets.load_texture(&file) {
+                        Ok(texture) => {
+                            self.glyphs.insert(ch, (font.repaint)(assets, texture));
+                            eprintln!("added {}", file.display());
                         }
-                        self.things[0].move_by(Vec3::new(SPEED, 0.0, 0.0));
+                        Err(err) => eprintln!("{}: {}", file.display(), err),
                     }
+                    dev.watcher.watch(&file);
+                    dev.paints.push(Paint {
+                        texture: file,
+                        on: Painted::Glyph(ch),
+                        repaint: font.repaint.clone(),
+                    });
                 }
-
-                for dooridx in self.state.rooms[self.state.current_room].doors.iter() {
-                    let door = self.state.doors[*dooridx as usize];
-                    if s.check_collisions(door) {
-                        self.state.current_room = door.target;
-                        s.trf.translation = get_spawn_pos(door.direction);
-                        s.tex_model.trf.translation = get_spawn_pos(door.direction);
-                        self.things[0].trf.translation = get_spawn_pos(door.direction);
-                        // dbg!(self.state.current_room);
-                    }
+                return;
+            }
+        }
+        if !dev.paints.iter().any(|paint| paint.texture == path) {
+            eprintln!("{} changed; restart the game to see it", path.display());
+            return;
+        }
+        let texture = match assets.load_texture(path) {
+            Ok(texture) => texture,
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                return;
+            }
+        };
+        for paint in dev.paints.iter().filter(|paint| paint.texture == path) {
+            let model = (paint.repaint)(assets, texture.clone());
+            match &paint.on {
+                Painted::Model(name) => dev.models.insert(name, model),
+                Painted::Room(theme) => self.themes[*theme].room = model,
+                Painted::Door(theme) => {
+                    self.themes[*theme].door = model;
+                    self.themes[*theme].door_texture = Some(path.to_path_buf());
                 }
-
-                //checking collision with key
-                if self.state.current_room == self.state.key_index
-                    && s.check_item_collisions(
-                        self.things[0].get_dir(),
-                        7.75,
-                        3.0,
-                        &self.textured[1],
-                    )
-                {
-                    //dbg!({ "" }, self.textured[0].trf.translation);
-                    self.state.has_key = true;
+                Painted::Glyph(ch) => {
+                    self.glyphs.insert(*ch, model);
                 }
+            }
+        }
+        if let Err(err) = self.use_models(&dev.models) {
+            eprintln!("{}", err);
+        }
+        eprintln!("reloaded {}", path.display());
+    }
+
+    //points everything drawn with a model from the manifest at its latest paint
+    fn use_models(
+        &mut self,
+        models: &Registry<Rc<frenderer::renderer::textured::Model>>,
+    ) -> std::result::Result<(), AssetError> {
+        for (shown, name) in self.main_screen_textured.iter_mut().zip([
+            "main_screen",
+            "instructions",
+            "final_screen",
+        ]) {
+            shown.model = models.get(name)?;
+        }
+        for (shown, name) in self.textured.iter_mut().zip(["block", "key", "chest"]) {
+            shown.model = models.get(name)?;
+        }
+        for theme in self.themes.iter_mut() {
+            if theme.door_texture.is_none() {
+                theme.door = models.get("door")?;
+            }
+        }
+        self.door_models = DoorModels {
+            open: models.get("door_open").ok(),
+            locked: models.get("door_locked").ok(),
+        };
+        self.curtain = models.get("curtain")?;
+        self.partition = models.get("partition")?;
+        self.stairs = models.get("stairs")?;
+        self.ghost = models.get("ghost")?;
+        self.marks = DoorMarks {
+            breadcrumb: models.get("breadcrumb")?,
+            dead_end: models.get("dead_end")?,
+        };
+        self.looks = RoomLooks::look_up(models)?;
+        self.map.room = models.get("map_room")?;
+        self.map.here = models.get("map_here")?;
+        self.map.door = models.get("map_door")?;
+        return Ok(());
+    }
 
-                //if we have the key, are in first room, and are collided with the chest
-                if self.state.current_room == 0
-                    && self.state.has_key
-                    && s.check_item_collisions(
-                        self.things[0].get_dir(),
-                        7.75,
-                        7.75,
-                        &self.textured[0],
-                    )
-                {
-                    self.state.is_finished = true;
-                    self.state.gameplaystate = GameplayState::FinalScreen;
+    //on the main screen the bot plays a house by itself after a while
+    fn update_demo(&mut self, controls: Controls) {
+        if self.state.gameplaystate != GameplayState::Mainscreen || controls != Controls::default()
+        {
+            self.idle = 0.0;
+            if self.demo.take().is_some() {
+                self.camera = start_camera();
+            }
+            return;
+        }
+        self.idle += DT;
+        if self.idle < ATTRACT_DELAY {
+            return;
+        }
+        if self.demo.as_ref().is_none_or(|demo| demo.state.is_finished) {
+            if self.demo.is_none() {
+                self.camera
+                    .transform
+                    .prepend_rotation(Rotor3::from_rotation_xz(PI / 4.0));
+            }
+            let house = self.config.house;
+            let mut state = GameState::generate_with(
+                house.generator,
+                house.rooms,
+                house.dead_ends,
+                house.floors,
+                self.state.num_tex,
+                &mut rand::thread_rng(),
+            );
+            state.tuning = self.state.tuning;
+            self.demo = Some(Demo {
+                state,
+                bot: Explorer::new(),
+            });
+        }
+        let demo = self.demo.as_mut().unwrap();
+        let bot_controls = demo.bot.controls(&demo.state);
+        let events = rules::update(&mut demo.state, bot_controls, &mut rand::thread_rng());
+        if events.contains(&Event::Moved) {
+            self.things[0].tick_animation();
+        }
+    }
+}
+
+impl frenderer::World for World {
+    fn update(&mut self, input: &frenderer::Input, assets: &mut frenderer::assets::Assets) {
+        self.hot_reload(assets);
+
+        if self.audio_play {
+            if let Err(err) = self.audio[0].play(InstanceSettings::default()) {
+                eprintln!("can't play the ghost choir: {}", err);
+            }
+            self.audio_play = false;
+        }
+
+        // The house stands still during a transition: no ghost moves and no
+        // clock runs. Those ticks aren't simulated, so they aren't recorded
+        // either, and a replay only feeds its inputs in between them.
+        let in_transition = self.passage.is_some() || self.screen_change.is_some();
+        //the replay is let go on its last tick, before what that tick did is kept
+        let replayed = self.replay.is_some();
+        let controls = match &mut self.replay {
+            _ if in_transition => Controls::default(),
+            Some(replay) => replay.inputs.controls(&self.state),
+            None => read_controls(input),
+        };
+        let from = self.state.gameplaystate;
+        let at_door = self.state.player;
+        let events = if in_transition {
+            vec![]
+        } else {
+            //a replay reloads config.toml wherever the recording did
+            if let Some(replay) = &self.replay {
+                for retune in replay.recording.retunes_at(self.recorder.ticks()) {
+                    self.recorder.retune(*retune);
+                    retune.apply(&mut self.state);
                 }
             }
+            self.recorder.push(controls);
+            rules::update(&mut self.state, controls, &mut self.rng)
+        };
+        for event in events.iter() {
+            match event {
+                Event::Moved => self.things[0].tick_animation(),
+                Event::Restarted => {
+                    self.seeded = false;
+                    self.has_rotated = false;
+                    self.passage = None;
+                    self.play_ticks = 0;
+                    self.split = None;
+                    self.summary = None;
+                    self.entered = 0.0;
+                    self.map.layout = minimap::layout(&self.state);
+                    self.screen_change = Some(ScreenChange {
+                        from,
+                        transition: self.screen_transition(),
+                    });
+                }
+                //back to the first room, or on to the final screen
+                Event::Caught => {
+                    self.passage = None;
+                    self.entered = 0.0;
+                    self.screen_change = Some(ScreenChange {
+                        from,
+                        transition: self.screen_transition(),
+                    });
+                }
+                Event::ScreenChanged(_) => {
+                    self.entered = 0.0;
+                    self.screen_change = Some(ScreenChange {
+                        from,
+                        transition: self.screen_transition(),
+                    });
+                }
+                Event::DoorTraversed { door, from, to } => {
+                    let mut before = self.state.clone();
+                    before.current_room = *from;
+                    before.player = at_door;
+                    let back = self
+                        .state
+                        .room_doors(*to)
+                        .find(|(_, back)| back.target == *from)
+                        .map_or(*door, |(idx, _)| idx);
+                    let transitions = self.config.transitions;
+                    self.passage = Some(Passage {
+                        before,
+                        door: *door,
+                        back,
+                        transition: Transition::new(transitions.room, transitions.room_time),
+                    });
+                    self.entered = 0.0;
+                }
+                _ => {}
+            }
+        }
+        if !in_transition && self.state.gameplaystate.is_playing() {
+            self.play_ticks += 1;
+        }
+        if let Some(passage) = &mut self.passage {
+            passage.transition.tick(DT);
+            if passage.transition.is_done() {
+                self.passage = None;
+            }
+        } else if self.screen_change.is_none() {
+            self.entered += DT;
+        }
+        if let Some(change) = &mut self.screen_change {
+            change.transition.tick(DT);
+            if change.transition.is_done() {
+                self.screen_change = None;
+            }
+        }
+
+        //controls for gameplaystate play
+        if self.state.gameplaystate.is_playing() && !self.has_rotated {
+            self.camera
+                .transform
+                .prepend_rotation(Rotor3::from_rotation_xz(PI / 4.0));
+            self.has_rotated = true;
+        }
+
+        self.update_demo(controls);
 
-            // let camera_drot = input.key_axis(Key::Left, Key::Right) * PI / 4.0 * DT as f32;
-            // dbg!({ "" }, self.camera.transform.translation);
-            // dbg!({ "" }, self.camera.transform.rotation);
-            // self.camera
-            //     .transform
-            //     .prepend_rotation(Rotor3::from_rotation_xz(-0.3887));
+        if self
+            .replay
+            .as_ref()
+            .is_some_and(|replay| replay.inputs.is_done())
+        {
+            let replay = self.replay.take().unwrap();
+            let end = Snapshot::of(&self.state, replay.recording.frames.len());
+            if end == replay.recording.end {
+                eprintln!("replay matched: {}", end);
+            } else {
+                eprintln!(
+                    "replay diverged\n  recorded: {}\n  replayed: {}",
+                    replay.recording.end, end
+                );
+            }
+        }
+        //a house against the clock goes up against the best times, and joins them if it
+        //beat them; only the seed's own house can be played again, and replays are old news
+        if events.contains(&Event::ChestOpened) && self.seeded && !replayed {
+            if let Some(ticks) = self.state.clock {
+                let rooms = self.state.max_rooms;
+                self.split = Some(self.best_times.record(self.seed, rooms, ticks));
+                if let Err(err) = self.best_times.save(&self.times_path) {
+                    eprintln!("{}: {}", self.times_path.display(), err);
+                }
+            }
+        }
+        //and the daily house goes in the history, just the once
+        if events.contains(&Event::ChestOpened) {
+            if let (Some(date), Some(ticks)) = (self.daily, self.state.clock) {
+                self.history.record(date, ticks);
+                if let Err(err) = self.history.save(&self.history_path) {
+                    eprintln!("{}: {}", self.history_path.display(), err);
+                }
+                self.summary = Some(text::daily_summary(&self.history, date, ticks));
+                self.daily = None;
+            }
+        }
+        //every level of the endless game that ends, won or lost, is where the next session
+        //starts, unless it was only a replay of one
+        if events.contains(&Event::ScreenChanged(GameplayState::FinalScreen))
+            && self.state.levels.is_some()
+            && !replayed
+        {
+            self.progress
+                .record(self.state.level, self.state.is_finished);
+            if let Err(err) = self.progress.save(&self.progress_path) {
+                eprintln!("{}: {}", self.progress_path.display(), err);
+            }
+        }
+        //keep a recording of every finished house, and of anything odd on F9
+        if events.contains(&Event::ChestOpened) || input.is_key_pressed(Key::F9) {
+            let recording = self.recorder.finish(&self.state);
+            match recording.save(&self.record_path) {
+                Ok(()) => eprintln!("saved recording to {}", self.record_path.display()),
+                Err(err) => eprintln!("{}: {}", self.record_path.display(), err),
+            }
         }
-        //restart the game by pressing S, and randomize
-        else if self.state.gameplaystate == GameplayState::FinalScreen {
-            if input.is_key_down(Key::R) {
-                self.state = restart(self.state.max_rooms, &self.state.wallpapers);
+
+        //the character model follows the simulated player
+        let shown = shown(&self.demo, &self.passage, &self.state);
+        self.things[0].trf.translation = shown.player.pos;
+        self.things[0].trf.rotation = get_facing(shown.player.dir);
+        sync_doors(&mut self.doors, shown);
+        if let Some(passage) = &self.passage {
+            let (swinging, amount) = passage.swing();
+            for door in self.doors.iter_mut() {
+                if door.door == swinging {
+                    let dir = shown.doors[swinging].direction;
+                    door.trf = swing_door(door.trf, dir, shown.tuning.door_reach(), amount);
+                    door.swing = amount;
+                }
             }
         }
     }
+
     fn render(
         &mut self,
         _a: &mut frenderer::assets::Assets,
         rs: &mut frenderer::renderer::RenderState,
     ) {
-        rs.set_camera(self.camera);
+        let screen = match &self.screen_change {
+            Some(change) if !change.transition.shows_new() => change.from,
+            _ => self.state.gameplaystate,
+        };
+        //menus are always seen straight on, even while play's camera turns
+        let mut camera = match screen {
+            GameplayState::Play | GameplayState::TimeAttack => self.camera,
+            GameplayState::Mainscreen if self.demo.is_some() => self.camera,
+            _ => start_camera(),
+        };
+        //lean in towards the door on the way out of a room, and up or down a
+        //staircase on the way out and on into the next room
+        if let Some(passage) = &self.passage {
+            let (door, amount) = passage.swing();
+            let before = &passage.before;
+            let lift = Vec3::new(0.0, climb(before.doors[passage.door]) * STAIR_CLIMB, 0.0);
+            if !passage.transition.shows_new() {
+                let shape = before.rooms[before.current_room].shape;
+                let toward = door_pos(shape, before.doors[door], before.tuning.room_size);
+                camera
+                    .transform
+                    .prepend_translation(-(toward + lift) * (amount * 0.25));
+            } else {
+                //arriving from below after going up, from above after going down
+                camera.transform.prepend_translation(lift * (amount * 0.25));
+            }
+        }
+        rs.set_camera(camera);
+        self.render_curtain(&camera, rs);
 
         //gameplaystate:: Mainscreen
         //could do a match instead
-        if self.state.gameplaystate == GameplayState::Mainscreen {
+        if screen == GameplayState::Mainscreen {
+            if let Some(demo) = &self.demo {
+                self.render_house(&demo.state, rs);
+                return;
+            }
             rs.render_textured(
                 0,
                 self.main_screen_textured[0].model.clone(),
                 FTextured::new(self.main_screen_textured[0].trf),
             );
-        } else if self.state.gameplaystate == GameplayState::Instructions {
+        } else if screen == GameplayState::Instructions {
             rs.render_textured(
                 1,
                 self.main_screen_textured[1].model.clone(),
                 FTextured::new(self.main_screen_textured[1].trf),
             );
+            let lines = text::instructions();
+            let (cols, _) = text::extent(&lines);
+            let corner = Vec2::new(-(cols as f32) * TEXT_CELL / 2.0, -6.0);
+            self.render_text(&camera, &lines, corner, TEXT_IDS, rs);
         }
         //gameplaystate:: play
-        else if self.state.gameplaystate == GameplayState::Play {
-            //render the doors in the correct positions
-            let door_list = &self.state.rooms[self.state.current_room].doors;
-
-            //render the key if we are in the right room and if we don't have the key
-            if self.state.key_index == self.state.current_room && !self.state.has_key {
-                //render the key
-                rs.render_textured(
-                    10,
-                    self.textured[1].model.clone(),
-                    FTextured::new(self.textured[1].trf),
-                );
-            }
-            //render the chest in the starting room
-            else if self.state.current_room == 0 {
-                //render the block
-                // rs.render_textured(
-                //     9,
-                //     self.textured[0].model.clone(),
-                //     FTextured::new(self.textured[0].trf),
-                // );
-
-                rs.render_textured(
-                    10,
-                    self.textured[2].model.clone(),
-                    FTextured::new(self.textured[2].trf),
-                );
+        else if screen.is_playing() {
+            self.render_house(shown(&self.demo, &self.passage, &self.state), rs);
+            let ticks = self.state.clock.unwrap_or(self.play_ticks);
+            let hud = text::hud(&self.state, self.seed, ticks);
+            self.render_text(&camera, &hud, Vec2::new(-12.0, 8.5), TEXT_IDS, rs);
+            self.render_minimap(&camera, rs);
+            if self.passage.is_none() && self.entered < NAME_TIME {
+                let lines = text::room_name(&self.state);
+                let (cols, _) = text::extent(&lines);
+                let corner = Vec2::new(-(cols as f32) * TEXT_CELL / 2.0, 6.0);
+                //after the HUD's ids, which never run past a few dozen
+                self.render_text(&camera, &lines, corner, TEXT_IDS + 500, rs);
             }
+        } else if screen == GameplayState::FinalScreen {
+            self.camera = start_camera();
 
-            //place doors
-            if door_list.len() > 0 {
-                rs.render_textured(
-                    0 as usize,
-                    self.door1.model.clone(),
-                    FTextured::new(get_trf(
-                        self.state.doors[door_list[0]].direction,
-                        ROOMSIZE,
-                        self.door1.trf.scale,
-                    )),
-                );
-            }
-            if door_list.len() > 1 {
-                rs.render_textured(
-                    1 as usize,
-                    self.door2.model.clone(),
-                    FTextured::new(get_trf(
-                        self.state.doors[door_list[1]].direction,
-                        ROOMSIZE,
-                        self.door2.trf.scale,
-                    )),
-                );
+            rs.render_textured(
+                3,
+                self.main_screen_textured[2].model.clone(),
+                FTextured::new(self.main_screen_textured[2].trf),
+            );
+            let ticks = self.state.clock.unwrap_or(self.play_ticks);
+            let mut lines = text::final_screen(&self.state, self.seed, ticks);
+            if let Some(summary) = &self.summary {
+                lines.extend(summary.iter().cloned());
+            } else if let Some(split) = &self.split {
+                lines.extend(text::splits(split));
             }
-            if door_list.len() > 2 {
-                rs.render_textured(
-                    2 as usize,
-                    self.door3.model.clone(),
-                    FTextured::new(get_trf(
-                        self.state.doors[door_list[2]].direction,
-                        ROOMSIZE,
-                        self.door3.trf.scale,
-                    )),
-                );
-            }
-            if door_list.len() > 3 {
-                rs.render_textured(
-                    3 as usize,
-                    self.door4.model.clone(),
-                    FTextured::new(get_trf(
-                        self.state.doors[door_list[3]].direction,
-                        ROOMSIZE,
-                        self.door4.trf.scale,
-                    )),
-                );
+            if self.state.levels.is_some() {
+                lines.extend(text::progress_lines(&self.state, &self.progress));
             }
+            let (cols, _) = text::extent(&lines);
+            let corner = Vec2::new(-(cols as f32) * TEXT_CELL / 2.0, -4.0);
+            self.render_text(&camera, &lines, corner, TEXT_IDS, rs);
+        }
+    }
+}
+//the slot-th mark up a door, on the room side, facing into the room
+fn mark_trf(shape: Shape, door: Door, tuning: &Tuning, slot: usize) -> Similarity3 {
+    let wall = door_pos(shape, door, tuning.room_size);
+    let inward = get_inward(door.direction);
+    let up = Vec3::new(0.0, MARK_HEIGHT + slot as f32 * MARK_GAP, 0.0);
+    return Similarity3::new(wall + inward * MARK_OUT + up, face(inward), MARK_SIZE);
+}
 
-            //render the game object
-            rs.render_skinned(
-                5 as usize,
-                self.things[0].model.clone(),
-                FSkinned::new(
-                    self.things[0].animation,
-                    self.things[0].state,
-                    self.things[0].trf,
-                ),
-            );
+//which way a door takes you: 1 up a flight, -1 down one, 0 on the level
+fn climb(door: Door) -> f32 {
+    return match door.stairs {
+        Some(Stairs::Up) => 1.0,
+        Some(Stairs::Down) => -1.0,
+        None => 0.0,
+    };
+}
 
-            rs.render_skinned(
-                7 as usize,
-                self.things[0].model.clone(),
-                FSkinned::new(
-                    self.things[0].animation,
-                    self.things[0].state,
-                    self.things[0].trf,
-                ),
-            );
+//turns a flat model, which faces +z, to face dir on the floor plane
+fn face(dir: Vec3) -> Rotor3 {
+    //rotation_between can't pick a way round for -z
+    if dir.z < -0.5 {
+        return Rotor3::from_rotation_xz(PI);
+    }
+    return Rotor3::from_rotation_between(Vec3::unit_z(), dir);
+}
 
-            //render room
-            rs.render_textured(
-                6 as usize,
-                self.state.wallpapers[self.state.rooms[self.state.current_room].tex_idx].clone(),
-                FTextured::new(Similarity3::new(
-                    Vec3::new(0.0, ROOMSIZE / 2., 0.0),
-                    Rotor3::from_euler_angles(0.0, 0.0, 0.0),
-                    ROOMSIZE / 2.,
-                )),
-            );
+//a flat model at pos in view space, turned to face the camera
+fn in_front(camera: &Camera, pos: Vec3, scale: f32) -> Similarity3 {
+    //the camera's transform takes the world into view space, so undo it
+    let to_world = camera.transform.inversed();
+    return Similarity3::new(to_world.transform_vec(pos), to_world.rotation, scale);
+}
 
-            // render the sprites
-            // rs.render_textured(
-            //     8,
-            //     self.sprites[0].tex_model.model.clone(),
-            //     FTextured::new(self.sprites[0].tex_model.trf),
-            // );
-        } else if self.state.gameplaystate == GameplayState::FinalScreen {
-            self.camera = Camera::look_at(
-                Vec3::new(0., 40.0, 100.),
-                Vec3::new(0., 40.0, 0.),
-                Vec3::new(0., 1., 0.),
-                camera::Projection::Perspective { fov: PI / 2.0 },
-            );
+//the state on screen: the attract mode house, or the room being left
+fn shown<'a>(
+    demo: &'a Option<Demo>,
+    passage: &'a Option<Passage>,
+    state: &'a GameState,
+) -> &'a GameState {
+    return match (demo, passage) {
+        (Some(demo), _) => &demo.state,
+        (None, Some(passage)) if !passage.transition.shows_new() => &passage.before,
+        (None, _) => state,
+    };
+}
 
-            rs.render_textured(
-                3,
-                self.main_screen_textured[2].model.clone(),
-                FTextured::new(self.main_screen_textured[2].trf),
-            );
+//one instance per door of the current room, opened as the player walks up
+fn sync_doors(doors: &mut Vec<DoorInstance>, state: &GameState) {
+    let room = &state.rooms[state.current_room];
+    if !doors
+        .iter()
+        .map(|door| door.door)
+        .eq(room.doors.iter().copied())
+    {
+        *doors = room
+            .doors
+            .iter()
+            .map(|&door| DoorInstance {
+                door,
+                trf: door_trf(room.shape, state.doors[door], &state.tuning),
+                look: DoorLook::Closed,
+                swing: 0.0,
+            })
+            .collect();
+    }
+    let reach = state.tuning.door_reach() + state.tuning.buffer;
+    for door in doors.iter_mut() {
+        //--dev can change the tuning under a room
+        door.trf = door_trf(room.shape, state.doors[door.door], &state.tuning);
+        door.swing = 0.0;
+        if state.doors[door.door].locked && !state.has_key {
+            door.look = DoorLook::Locked;
+            continue;
         }
+        let offset = door.trf.translation - state.player.pos;
+        let near = Vec2::new(offset.x, offset.z).mag() < reach;
+        door.look = if near {
+            DoorLook::Open
+        } else {
+            DoorLook::Closed
+        };
     }
 }
+
 fn main() -> Result<()> {
     frenderer::color_eyre::install()?;
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        std::process::exit(2);
+    });
+    let config = options
+        .load_config()
+        .map_err(|message| frenderer::color_eyre::eyre::eyre!(message))?;
+    //check every file before opening a window
+    let manifest_path = Path::new("content/assets.toml");
+    let manifest = Manifest::load(manifest_path)?;
 
     let mut engine: Engine = Engine::new(
         FrendererSettings {
@@ -523,271 +1304,82 @@ fn main() -> Result<()> {
         DT,
     );
 
-    let camera = Camera::look_at(
-        Vec3::new(0., 40.0, 100.),
-        Vec3::new(0., 40.0, 0.),
-        Vec3::new(0., 1., 0.),
-        camera::Projection::Perspective { fov: PI / 2.0 },
-    );
-
-    //character model
-    let tex = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/robot.png"))?;
-    let model = engine
-        .assets()
-        .load_textured(std::path::Path::new("content/characterSmall.fbx"))?;
-    let char_model = engine.assets().create_textured_model(model, vec![tex]);
-
-    //door model
-    let door_tex = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/floor.png"))?;
-    let door = engine
-        .assets()
-        .load_textured(std::path::Path::new("content/door.fbx"))?;
-    let door_model = engine.assets().create_textured_model(door, vec![door_tex]);
-
-    // room model
-    let room_tex = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/tex2.png"))?;
-    let room = engine
-        .assets()
-        .load_textured(std::path::Path::new("content/room.fbx"))?;
-    let room_model = engine
-        .assets()
-        .create_textured_model(room.clone(), vec![room_tex]);
-
-    let room_tex1 = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/tex3.png"))?;
-    let room_model1 = engine
-        .assets()
-        .create_textured_model(room.clone(), vec![room_tex1]);
-
-    let room_tex2 = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/tex4.png"))?;
-    let room_model2 = engine
-        .assets()
-        .create_textured_model(room.clone(), vec![room_tex2]);
-
-    let room_tex3 = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/tex5.png"))?;
-    let room_model3 = engine
-        .assets()
-        .create_textured_model(room.clone(), vec![room_tex3]);
-
-    let room_tex4 = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/tex6.png"))?;
-    let room_model4 = engine
-        .assets()
-        .create_textured_model(room.clone(), vec![room_tex4]);
-
-    let room_tex5 = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/tex7.png"))?;
-    let room_model5 = engine
-        .assets()
-        .create_textured_model(room.clone(), vec![room_tex5]);
-
-    let room_tex6 = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/tex8.png"))?;
-    let room_model6 = engine
-        .assets()
-        .create_textured_model(room.clone(), vec![room_tex6]);
-
-    let room_tex7 = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/tex9.png"))?;
-    let room_model7 = engine
-        .assets()
-        .create_textured_model(room.clone(), vec![room_tex7]);
-
-    let key_tex = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/blue color.png"))?;
-    let key_mesh = engine
-        .assets()
-        .load_textured(std::path::Path::new("content/key please.fbx"))?;
-    let key_model = engine
-        .assets()
-        .create_textured_model(key_mesh, vec![key_tex]);
-
-    let block_tex = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/gold metal .png"))?;
-    let block_mesh = engine
-        .assets()
-        .load_textured(std::path::Path::new("content/block y.fbx"))?;
-    let block_model = engine
-        .assets()
-        .create_textured_model(block_mesh, vec![block_tex]);
-
-    let chest_tex = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/minecraft chest tex.png"))?;
-    let chest_mesh = engine
-        .assets()
-        .load_textured(std::path::Path::new("content/minecraft chest y up.fbx"))?;
-    let chest_model = engine
-        .assets()
-        .create_textured_model(chest_mesh, vec![chest_tex]);
-
-    let text_plane_main = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/main screen tex.png"))?;
-
-    let text_plane_main_screen_mesh = engine
-        .assets()
-        .load_textured(std::path::Path::new("content/text_plane.fbx"))?;
-
-    let text_plane_main_screen_model = engine
-        .assets()
-        .create_textured_model(text_plane_main_screen_mesh, vec![text_plane_main]);
-
-    let text_plane_instructions_mesh = engine
-        .assets()
-        .load_textured(std::path::Path::new("content/text_plane.fbx"))?;
-
-    let text_plane_instructions = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/instructions tex.png"))?;
-
-    let text_plane_instructions_model = engine
-        .assets()
-        .create_textured_model(text_plane_instructions_mesh, vec![text_plane_instructions]);
-
-    let text_plane_final_mesh = engine
-        .assets()
-        .load_textured(std::path::Path::new("content/text_plane.fbx"))?;
-
-    let text_plane_final = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/final tex.png"))?;
-
-    let text_plane_final_model = engine
-        .assets()
-        .create_textured_model(text_plane_final_mesh, vec![text_plane_final]);
-
+    let camera = start_camera();
     let mut audio_manager = AudioManager::new(AudioManagerSettings::default()).unwrap();
-    let ghost_choir = audio_manager
-        .load_sound("content/ghost-choir looped.ogg", SoundSettings::default())
-        .unwrap();
-
-    //code for skinned model and gameObject
-    let sprite_meshes = engine.assets().load_skinned(
-        std::path::Path::new("content/characterSmall.fbx"),
-        &["RootNode", "Root"],
-    )?;
-
-    let sprite_animation_run = engine.assets().load_anim(
-        std::path::Path::new("content/run.fbx"),
-        sprite_meshes[0],
-        AnimationSettings { looping: true },
-        "Root|Run",
-    )?;
-
-    let sprite_animation_stand = engine.assets().load_anim(
-        std::path::Path::new("content/idle.fbx"),
-        sprite_meshes[0],
-        AnimationSettings { looping: false },
-        "Root|Idle",
-    )?;
-
-    let animation_vec = vec![sprite_animation_run, sprite_animation_stand];
-
-    let sprite_texture = engine
-        .assets()
-        .load_texture(std::path::Path::new("content/robot2.png"))?;
-    let sprite_model = engine
-        .assets()
-        .create_skinned_model(sprite_meshes, vec![sprite_texture]);
+    let assets = load_assets(&mut engine, &mut audio_manager, &manifest)?;
+
+    let door_models = DoorModels {
+        open: assets.models.get("door_open").ok(),
+        locked: assets.models.get("door_locked").ok(),
+    };
+    let block_model = assets.models.get("block")?;
+    let key_model = assets.models.get("key")?;
+    let chest_model = assets.models.get("chest")?;
+    let text_plane_main_screen_model = assets.models.get("main_screen")?;
+    let text_plane_instructions_model = assets.models.get("instructions")?;
+    let text_plane_final_model = assets.models.get("final_screen")?;
+    let ghost_choir = assets.sounds.get("ghost_choir")?;
+    let themes = assets.themes;
 
     // sprite gameobject
     let sprite_obj = GameObject::new(
         Similarity3::new(
             Vec3::new(20.0, 0.0, 0.0),
-            Rotor3::from_euler_angles(0.0, 0.0, PI as f32),
+            Rotor3::from_euler_angles(0.0, 0.0, PI),
             0.05,
         ),
-        sprite_model,
+        assets.characters.get("robot")?,
         //run animation
-        animation_vec[0],
+        assets.animations.get("run")?,
         AnimationState { t: 0.0 },
     );
 
-    let game_sprite = Sprite {
-        trf: Isometry3::new(
-            Vec3::new(20.0, 0.0, 0.0),
-            Rotor3::from_euler_angles(-PI / 2.0, -PI / 2.0, 0.0),
-        ), //change to Rotor3::identity() to see the plane
-        size: Vec2::new(16.0, 16.0),
-        cel: Rect::new(0.5, 0.0, 0.5, 0.5),
-        tex: tex,
-        tex_model: Textured {
-            trf: Similarity3::new(Vec3::new(20.0, 0.0, 0.0), Rotor3::identity(), 0.01),
-            model: char_model.clone(),
-            name: String::from("Sprite"),
-        },
+    //create n rooms, either fresh or the ones a recording was made in
+    let replay = match &options.replay {
+        Some(path) => Some(
+            Recording::load(path)
+                .map_err(|err| frenderer::color_eyre::eyre::eyre!("{}: {}", path.display(), err))?,
+        ),
+        None => None,
     };
-
-    //create n rooms
-    let (room_list, door_list) = generate_room_map(NUM_ROOMS as u32, 2, NUM_WALLPAPERS);
-
-    let game_state = GameState {
-        current_room: 0, //index of room in rooms
-        max_rooms: 4,
-        key_index: 3,
-        //inventory: vec![],
-        // rooms: vec![Room::new(vec![0]), Room::new(vec![1])],
-        rooms: room_list,
-        doors: door_list,
-        // doors: vec![
-        //     Door::new(Direction::North, 1, Direction::South),
-        //     Door::new(Direction::South, 0, Direction::North),
-        // ],
-        is_finished: false,
-        has_key: false,
-        gameplaystate: GameplayState::Mainscreen,
-        audio_play: true,
-        has_rotated: false,
-        wallpapers: vec![
-            room_model.clone(),
-            room_model1.clone(),
-            room_model2.clone(),
-            room_model3.clone(),
-            room_model4.clone(),
-            room_model5.clone(),
-            room_model6.clone(),
-            room_model7.clone(),
-        ],
+    let progress = Progress::load_or_default(&options.progress).map_err(|err| {
+        frenderer::color_eyre::eyre::eyre!("{}: {}", options.progress.display(), err)
+    })?;
+    //the endless game carries on where the last session left off
+    let mut options = options;
+    if options.endless {
+        options.level = options.level.or(Some(progress.level));
+    }
+    let today = (options.daily && replay.is_none()).then(daily::today);
+    let setup = match (&replay, today) {
+        (Some(recording), _) => recording.setup,
+        (None, Some(date)) => daily::setup(date),
+        (None, None) => options.setup(&config, rand::random(), themes.len()),
     };
-
-    let world = World {
+    let (game_state, rng) = setup.build();
+    let best_times = BestTimes::load_or_default(&options.times).map_err(|err| {
+        frenderer::color_eyre::eyre::eyre!("{}: {}", options.times.display(), err)
+    })?;
+    let history = History::load_or_default(&options.history).map_err(|err| {
+        frenderer::color_eyre::eyre::eyre!("{}: {}", options.history.display(), err)
+    })?;
+
+    let mut world = World {
         camera,
         audio: vec![ghost_choir],
         things: vec![sprite_obj],
-        sprites: vec![game_sprite],
         main_screen_textured: vec![
             Textured {
                 trf: Similarity3::new(Vec3::new(0.0, 30.0, 0.0), Rotor3::identity(), 80.0),
                 model: text_plane_main_screen_model.clone(),
-                name: String::from("main screen text plane"),
             },
             Textured {
                 trf: Similarity3::new(Vec3::new(0.0, 30.0, 0.0), Rotor3::identity(), 80.0),
                 model: text_plane_instructions_model.clone(),
-                name: String::from("instructions text plane"),
             },
             Textured {
                 trf: Similarity3::new(Vec3::new(0.0, 30.0, 0.0), Rotor3::identity(), 80.0),
                 model: text_plane_final_model.clone(),
-                name: String::from("final text plane"),
             },
         ],
 
@@ -796,12 +1388,10 @@ fn main() -> Result<()> {
             Textured {
                 trf: Similarity3::new(Vec3::new(0.0, 5.0, 0.0), Rotor3::identity(), 5.0),
                 model: block_model.clone(),
-                name: String::from("block model"),
             },
             Textured {
                 trf: Similarity3::new(Vec3::new(0.0, 10.0, 0.0), Rotor3::identity(), 0.1),
                 model: key_model.clone(),
-                name: String::from("key model"),
             },
             Textured {
                 trf: Similarity3::new(
@@ -810,269 +1400,260 @@ fn main() -> Result<()> {
                     5.0,
                 ),
                 model: chest_model.clone(),
-                name: String::from("chest model"),
             },
         ],
-        door1: Textured {
-            trf: Similarity3::new(
-                Vec3::new(0.0, 0.0, 0.0),
-                Rotor3::from_euler_angles(0.0, 0.0, 0.0),
-                SCALE,
-            ),
-            model: door_model.clone(),
-            name: String::from("Door1"),
-        },
-        door2: Textured {
-            trf: Similarity3::new(
-                Vec3::new(0.0, 0.0, 0.0),
-                Rotor3::from_euler_angles(0.0, 0.0, 0.0),
-                SCALE,
-            ),
-            model: door_model.clone(),
-            name: String::from("Door2"),
+        doors: vec![],
+        door_models,
+        passage: None,
+        screen_change: None,
+        curtain: assets.models.get("curtain")?,
+        glyphs: assets.glyphs,
+        seed: setup.seed,
+        seeded: true,
+        play_ticks: 0,
+        best_times,
+        times_path: options.times.clone(),
+        split: None,
+        daily: today,
+        history,
+        history_path: options.history.clone(),
+        summary: None,
+        progress,
+        progress_path: options.progress.clone(),
+        partition: assets.models.get("partition")?,
+        stairs: assets.models.get("stairs")?,
+        ghost: assets.models.get("ghost")?,
+        looks: RoomLooks::look_up(&assets.models)?,
+        entered: 0.0,
+        marks: DoorMarks {
+            breadcrumb: assets.models.get("breadcrumb")?,
+            dead_end: assets.models.get("dead_end")?,
         },
-        door3: Textured {
-            trf: Similarity3::new(
-                Vec3::new(0.0, 0.0, 0.0),
-                Rotor3::from_euler_angles(0.0, 0.0, 0.0),
-                SCALE,
-            ),
-            model: door_model.clone(),
-            name: String::from("Door1"),
-        },
-        door4: Textured {
-            trf: Similarity3::new(
-                Vec3::new(0.0, 0.0, 0.0),
-                Rotor3::from_euler_angles(0.0, 0.0, 0.0),
-                SCALE,
-            ),
-            model: door_model.clone(),
-            name: String::from("Door2"),
+        map: Minimap {
+            layout: minimap::layout(&game_state),
+            room: assets.models.get("map_room")?,
+            here: assets.models.get("map_here")?,
+            door: assets.models.get("map_door")?,
         },
-        room: Textured {
-            trf: Similarity3::new(
-                Vec3::new(0.0, 0.0, 0.0),
-                Rotor3::from_euler_angles(0.0, 0.0, 0.0),
-                SCALE,
-            ),
-            model: room_model.clone(),
-            name: String::from("Room"),
-        },
-        door_collider: Vec2::new(DOOR_WIDTH * SCALE, DOOR_DEPTH * SCALE),
-        state: game_state,
-    };
-    engine.play(world)
-}
-
-//fix this so that max_rooms and key_index are randomized
-fn restart(
-    curr_number_rooms: usize,
-    wallpapers: &std::vec::Vec<std::rc::Rc<frenderer::renderer::textured::Model>>,
-) -> GameState {
-    let (room_list, door_list) = generate_room_map(
-        (curr_number_rooms + DIFFICULTY) as u32,
-        DIFFICULTY,
-        wallpapers.len(),
-    );
-    let mut rng = rand::thread_rng();
-    let keyidx = rng.gen_range(1..curr_number_rooms + DIFFICULTY);
-    // dbg!({ "key loca: " }, keyidx);
-    return GameState {
-        current_room: 0, //index of room in rooms
-        max_rooms: curr_number_rooms + DIFFICULTY,
-        key_index: keyidx,
-        rooms: room_list,
-        doors: door_list,
-        is_finished: false,
-        has_key: false,
-        gameplaystate: GameplayState::Play,
-        audio_play: false,
+        themes,
+        audio_play: !options.mute,
         has_rotated: false,
-        wallpapers: wallpapers.to_vec(),
+        demo: None,
+        idle: 0.0,
+        rng,
+        recorder: Recorder::new(setup),
+        record_path: options.record.clone(),
+        config,
+        dev: None,
+        replay: replay.map(|recording| Replay {
+            inputs: Scripted::new(recording.frames.clone()),
+            recording,
+        }),
+        state: game_state,
     };
-}
-
-fn get_trf(dir: Direction, room_size: f32, scale: f32) -> Similarity3 {
-    match dir {
-        Direction::North => Similarity3::new(
-            Vec3::new(0.0, 0.0, room_size / 2.),
-            Rotor3::from_euler_angles(0.0, -PI / 2.0, 0.0),
-            scale,
-        ),
-        Direction::East => Similarity3::new(
-            Vec3::new(room_size / 2., 0.0, 0.0),
-            Rotor3::from_euler_angles(PI / 2.0, -PI / 2.0, 0.0),
-            scale,
-        ),
-        Direction::South => Similarity3::new(
-            Vec3::new(0.0, 0.0, -room_size / 2.),
-            Rotor3::from_euler_angles(0.0, -PI / 2.0, 0.0),
-            scale,
-        ),
-        Direction::West => Similarity3::new(
-            Vec3::new(-room_size / 2., 0.0, 0.0),
-            Rotor3::from_euler_angles(-PI / 2.0, -PI / 2.0, 0.0),
-            scale,
-        ),
-        // Direction::Other(n) => n as usize,
+    if options.dev {
+        //every texture drawn, and the folders themes and glyphs are found in
+        let mut watcher = Watcher::new();
+        watcher.watch(&options.config_path());
+        watcher.watch(manifest_path);
+        for file in manifest.files() {
+            watcher.watch(&manifest.path(file));
+        }
+        for paint in assets.paints.iter() {
+            watcher.watch(&paint.texture);
+        }
+        let theme_dir = manifest.path(&manifest.room.themes);
+        watcher.watch(&theme_dir);
+        if let Some(font) = &assets.font {
+            watcher.watch(&font.dir);
+        }
+        eprintln!("watching {} files for changes", watcher.len());
+        world.dev = Some(DevReload {
+            watcher,
+            config_path: options.config_path(),
+            models: assets.models,
+            paints: assets.paints,
+            theme_dir,
+            font: assets.font,
+            timer: 0.0,
+        });
     }
+    engine.play(world)
 }
 
-fn get_dir(num: u32) -> Direction {
-    match num {
-        0 => Direction::North,
-        1 => Direction::East,
-        2 => Direction::South,
-        3 => Direction::West,
-        _other => Direction::West,
-    }
+//everything the manifest lists, loaded and looked up by name
+struct Assets {
+    models: Registry<Rc<frenderer::renderer::textured::Model>>,
+    themes: Vec<RoomTheme>, //in the order Room::tex_idx counts them
+    paints: Vec<Paint>,     //every texture above and what it is on
+    font: Option<Font>,
+    characters: Registry<Rc<frenderer::renderer::skinned::Model>>,
+    animations: Registry<AnimRef>,
+    sounds: Registry<SoundHandle>,
+    glyphs: HashMap<char, Rc<frenderer::renderer::textured::Model>>,
 }
 
-fn get_spawn_dir(dir: Direction) -> Direction {
-    match dir {
-        Direction::North => Direction::South,
-        Direction::South => Direction::North,
-        Direction::East => Direction::West,
-        Direction::West => Direction::East,
-    }
-}
+fn load_assets(
+    engine: &mut Engine,
+    audio_manager: &mut AudioManager,
+    manifest: &Manifest,
+) -> Result<Assets> {
+    //text_plane.fbx, room.fbx and door.fbx are shared, so each file loads once
+    let mut textures = Cache::new();
+    let mut meshes = Cache::new();
+    let mut assets = Assets {
+        models: Registry::new("model"),
+        themes: vec![],
+        paints: vec![],
+        font: None,
+        characters: Registry::new("character"),
+        animations: Registry::new("animation"),
+        sounds: Registry::new("sound"),
+        glyphs: HashMap::new(),
+    };
 
-fn get_spawn_pos(dir: Direction) -> Vec3 {
-    let spawn_dir = get_spawn_dir(dir);
-    let mut spawn_loca = get_trf(spawn_dir, ROOMSIZE, SCALE).translation;
-    //need to adjust so no oscilarting
-    if spawn_dir == Direction::North {
-        spawn_loca.z -= BUFFER;
-    } else if spawn_dir == Direction::South {
-        spawn_loca.z += BUFFER;
-    } else if spawn_dir == Direction::East {
-        spawn_loca.x -= BUFFER;
-    } else {
-        spawn_loca.x += BUFFER;
+    for (name, entry) in manifest.models.iter() {
+        let mesh = meshes.load(&manifest.path(&entry.mesh), |path| {
+            engine.assets().load_textured(path)
+        })?;
+        let file = manifest.path(&entry.texture);
+        let texture = textures.load(&file, |path| engine.assets().load_texture(path))?;
+        let repaint: Repaint =
+            Rc::new(move |store, texture| store.create_textured_model(mesh, vec![texture]));
+        assets
+            .models
+            .insert(name, repaint(engine.assets(), texture));
+        assets.paints.push(Paint {
+            texture: file,
+            on: Painted::Model(name.clone()),
+            repaint,
+        });
     }
-    return spawn_loca;
-}
 
-fn generate_room_map(
-    num_rooms: u32,
-    num_dead_ends: usize,
-    num_tex: usize,
-) -> (Vec<Room>, Vec<Door>) {
-    let mut rooms = Vec::<Room>::new();
-    let mut doors = Vec::<Door>::new();
-    let mut n = 0;
-    //create n rooms
-    let mut curr_dead_ends = 0;
-    let mut rng = rand::thread_rng();
-
-    while n < num_rooms - 1 {
-        //if we arent on the last room we can add a door
-        if rooms.len() == 0 {
-            let mut room = Room::new(vec![], rng.gen_range(0..num_tex)); //create room with the first door
-            let door = gen_valid_door(&room, n as usize + 1, &doors); //generate random door //NEED TO CHECK IF VALID DOOR
-            let back_door = create_bidirectional_door(door, n as usize); //generate door that points back at first door
-
-            doors.push(door); //add door to the list of doors
-            room.doors.push(n as usize); //add door to room
-            doors.push(back_door); //add door to the list of doors
-            let room2 = Room::new(vec![n as usize + 1], rng.gen_range(0..num_tex)); //create next room
-
-            rooms.push(room);
-            rooms.push(room2);
-        } else {
-            let room = &mut rooms[n as usize]; //get last room
-                                               // add another door
-            let door = gen_valid_door(&room, n as usize + 1, &doors); //generate random door //NEED TO CHECK IF VALID DOOR
-            doors.push(door); //add door to the list of doors
-            let num_doors = doors.len() - 1;
-            room.doors.push(num_doors as usize); //add door to room
-
-            let back_door = create_bidirectional_door(door, n as usize); //generate door that points back at first door
-            doors.push(back_door); //add door to the list of doors
-            let room2 = Room::new(vec![num_doors as usize + 1], rng.gen_range(0..num_tex)); //create next room
-            rooms.push(room2);
+    let room = meshes.load(&manifest.path(&manifest.room.mesh), |path| {
+        engine.assets().load_textured(path)
+    })?;
+    //themes with their own door.png repaint the door model's mesh
+    let door_mesh = meshes.load(&manifest.path(&manifest.model("door")?.mesh), |path| {
+        engine.assets().load_textured(path)
+    })?;
+    let repaint_room: Repaint =
+        Rc::new(move |store, texture| store.create_textured_model(room, vec![texture]));
+    let repaint_door: Repaint =
+        Rc::new(move |store, texture| store.create_textured_model(door_mesh, vec![texture]));
+    for (i, theme) in manifest.themes()?.into_iter().enumerate() {
+        let texture = textures.load(&theme.room, |path| engine.assets().load_texture(path))?;
+        let room_model = repaint_room(engine.assets(), texture);
+        assets.paints.push(Paint {
+            texture: theme.room.clone(),
+            on: Painted::Room(i),
+            repaint: repaint_room.clone(),
+        });
+        let door_model = match &theme.door {
+            Some(file) => {
+                let texture = textures.load(file, |path| engine.assets().load_texture(path))?;
+                repaint_door(engine.assets(), texture)
+            }
+            None => assets.models.get("door")?,
+        };
+        //a theme folder can be given a door.png while the game runs
+        let door_file = match &theme.door {
+            Some(file) => Some(file.clone()),
+            None if theme.room.ends_with("room.png") => Some(theme.room.with_file_name("door.png")),
+            None => None,
+        };
+        if let Some(file) = door_file {
+            assets.paints.push(Paint {
+                texture: file,
+                on: Painted::Door(i),
+                repaint: repaint_door.clone(),
+            });
         }
-        n += 1;
-        // dbg!({ "while 1" });
+        assets.themes.push(RoomTheme {
+            room: room_model,
+            door: door_model,
+            door_texture: theme.door,
+        });
     }
 
-    //generate dead ends
-    while curr_dead_ends < num_dead_ends {
-        let roomidx = rng.gen_range(0..rooms.len() - 1);
-        let srcroom = &mut rooms[roomidx]; //get room index
-                                           //gen new door
-        let door = gen_valid_door(srcroom, num_rooms as usize + curr_dead_ends, &doors);
-        doors.push(door);
-        srcroom.doors.push(doors.len() - 1);
-        //make room that points back to that door
-        let back_door = create_bidirectional_door(door, roomidx);
-        doors.push(back_door);
-        let dest_room = Room::new(vec![doors.len() - 1], rng.gen_range(0..num_tex)); //create next room
-        rooms.push(dest_room);
-
-        curr_dead_ends += 1;
-        // dbg!({ "while 2" });
+    //one model per glyph, since a model shows the whole of its texture
+    if let Some(font) = &manifest.font {
+        let mesh = meshes.load(&manifest.path(&font.mesh), |path| {
+            engine.assets().load_textured(path)
+        })?;
+        let repaint: Repaint =
+            Rc::new(move |store, texture| store.create_textured_model(mesh, vec![texture]));
+        for (ch, file) in manifest.glyphs()? {
+            let texture = textures.load(&file, |path| engine.assets().load_texture(path))?;
+            assets.glyphs.insert(ch, repaint(engine.assets(), texture));
+            assets.paints.push(Paint {
+                texture: file,
+                on: Painted::Glyph(ch),
+                repaint: repaint.clone(),
+            });
+        }
+        assets.font = Some(Font {
+            dir: manifest.path(&font.glyphs),
+            repaint,
+        });
     }
-    // dbg!(&doors);
-    // dbg!(&rooms);
-    return (rooms, doors);
-}
 
-fn gen_valid_door(room: &Room, target: usize, doors: &[Door]) -> Door {
-    let mut door = generate_door(target);
-    // if this is the first door return door
-    if room.doors.len() == 0 {
-        return door;
-    }
-    //else we need to make sure there are no repeats
-    let mut check = true; //unique direction
-    let mut check2 = true; //unique target
-    for n in 0..room.doors.len() {
-        if door.direction == doors[room.doors[n]].direction {
-            check = false;
-        }
-        if door.target == doors[room.doors[n]].target {
-            check2 = false;
-        }
+    //animations are bound to the first mesh of their character
+    let mut skeletons = HashMap::new();
+    for (name, entry) in manifest.characters.iter() {
+        let nodes: Vec<&str> = entry.nodes.iter().map(String::as_str).collect();
+        let character_meshes = engine
+            .assets()
+            .load_skinned(manifest.path(&entry.mesh).as_path(), &nodes)?;
+        let texture = textures.load(&manifest.path(&entry.texture), |path| {
+            engine.assets().load_texture(path)
+        })?;
+        skeletons.insert(name.as_str(), character_meshes[0]);
+        let model = engine
+            .assets()
+            .create_skinned_model(character_meshes, vec![texture]);
+        assets.characters.insert(name, model);
     }
-    while check == false || check2 == false {
-        door = generate_door(target);
-        for n in 0..room.doors.len() {
-            if door.direction == doors[room.doors[n]].direction {
-                check = false;
-            } else {
-                check = true;
-            }
-            if door.target == doors[room.doors[n]].target {
-                check2 = false;
-            } else {
-                check2 = true;
-            }
-        }
-        // dbg!({ "while 3" });
+    for (name, entry) in manifest.animations.iter() {
+        let anim = engine.assets().load_anim(
+            manifest.path(&entry.file).as_path(),
+            skeletons[entry.character.as_str()],
+            AnimationSettings {
+                looping: entry.looping,
+            },
+            &entry.clip,
+        )?;
+        assets.animations.insert(name, anim);
     }
-    return door;
-}
 
-fn check_valid_door(door: Door, room: Room, doors: Vec<Door>) -> bool {
-    //room is current room
-    let mut check = true;
-    for n in 0..room.doors.len() {
-        if door.direction == doors[room.doors[n]].direction {
-            check = false;
-        }
+    for (name, file) in manifest.sounds.iter() {
+        let path = manifest.path(file);
+        let sound = audio_manager
+            .load_sound(&path, SoundSettings::default())
+            .map_err(|err| frenderer::color_eyre::eyre::eyre!("{}: {}", path.display(), err))?;
+        assets.sounds.insert(name, sound);
     }
-    return check;
+    return Ok(assets);
 }
 
-//return a new door on oppoisite side that points back to the previous room
-fn create_bidirectional_door(door: Door, cur_room: usize) -> Door {
-    return Door::new(get_spawn_dir(door.direction), cur_room, door.direction);
+fn start_camera() -> Camera {
+    return Camera::look_at(
+        Vec3::new(0., 40.0, 100.),
+        Vec3::new(0., 40.0, 0.),
+        Vec3::new(0., 1., 0.),
+        camera::Projection::Perspective { fov: PI / 2.0 },
+    );
 }
 
-//generate a door with random direction and target
-fn generate_door(target: usize) -> Door {
-    let mut rng = rand::thread_rng();
-    let direction = get_dir(rng.gen_range(0..4));
-    return Door::new(direction, target, get_spawn_dir(direction));
+//the simulation only sees what each key means, not which key it is
+fn read_controls(input: &frenderer::Input) -> Controls {
+    return Controls {
+        up: input.is_key_down(Key::W),
+        down: input.is_key_down(Key::S),
+        left: input.is_key_down(Key::A),
+        right: input.is_key_down(Key::D),
+        play: input.is_key_down(Key::P),
+        time_attack: input.is_key_down(Key::T),
+        restart: input.is_key_down(Key::R),
+    };
 }
@@ -0,0 +1,766 @@
+use crate::geometry::*;
+use crate::identity;
+use crate::types::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// How the rooms of a house are wired together. Either way every room is
+// reachable from room 0 and the dead ends are hung off afterwards.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Generator {
+    Path, //one long corridor of rooms
+    Tree, //rooms branch off any earlier room
+}
+
+impl Generator {
+    pub fn generate<R: Rng>(
+        self,
+        num_rooms: u32,
+        num_dead_ends: usize,
+        num_tex: usize,
+        rng: &mut R,
+    ) -> (Vec<Room>, Vec<Door>) {
+        let (mut rooms, mut doors) = match self {
+            Generator::Path => generate_room_map(num_rooms, num_dead_ends, num_tex, rng),
+            Generator::Tree => generate_tree_map(num_rooms, num_dead_ends, num_tex, rng),
+        };
+        place_rooms(&mut rooms, &doors);
+        identity::assign(&mut rooms, &doors, rng);
+        shape_rooms(&mut rooms, &mut doors, rng);
+        return (rooms, doors);
+    }
+}
+
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Generator::Path => write!(f, "path"),
+            Generator::Tree => write!(f, "tree"),
+        };
+    }
+}
+
+impl FromStr for Generator {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        return match name {
+            "path" => Ok(Generator::Path),
+            "tree" => Ok(Generator::Tree),
+            _ => Err(format!(
+                "unknown generator {} (expected path or tree)",
+                name
+            )),
+        };
+    }
+}
+
+//builds a path of num_rooms rooms, then hangs num_dead_ends extra rooms off it
+pub fn generate_room_map<R: Rng>(
+    num_rooms: u32,
+    num_dead_ends: usize,
+    num_tex: usize,
+    rng: &mut R,
+) -> (Vec<Room>, Vec<Door>) {
+    let mut rooms = Vec::<Room>::new();
+    let mut doors = Vec::<Door>::new();
+    let mut n = 0;
+    //create n rooms
+
+    if num_rooms <= 1 {
+        rooms.push(Room::new(vec![], rng.gen_range(0..num_tex)));
+    }
+
+    while n + 1 < num_rooms {
+        //if we arent on the last room we can add a door
+        if rooms.is_empty() {
+            let mut room = Room::new(vec![], rng.gen_range(0..num_tex)); //create room with the first door
+            let door = gen_valid_door(&room, n as usize + 1, &doors, rng); //generate random door
+            let back_door = create_bidirectional_door(door, n as usize); //generate door that points back at first door
+
+            doors.push(door); //add door to the list of doors
+            room.doors.push(n as usize); //add door to room
+            doors.push(back_door); //add door to the list of doors
+            let room2 = Room::new(vec![n as usize + 1], rng.gen_range(0..num_tex)); //create next room
+
+            rooms.push(room);
+            rooms.push(room2);
+        } else {
+            let room = &mut rooms[n as usize]; //get last room
+                                               // add another door
+            let door = gen_valid_door(room, n as usize + 1, &doors, rng); //generate random door
+            doors.push(door); //add door to the list of doors
+            let num_doors = doors.len() - 1;
+            room.doors.push(num_doors); //add door to room
+
+            let back_door = create_bidirectional_door(door, n as usize); //generate door that points back at first door
+            doors.push(back_door); //add door to the list of doors
+            let room2 = Room::new(vec![num_doors + 1], rng.gen_range(0..num_tex)); //create next room
+            rooms.push(room2);
+        }
+        n += 1;
+    }
+
+    //generate dead ends
+    for _ in 0..num_dead_ends {
+        attach_room(&mut rooms, &mut doors, num_tex, rng);
+    }
+    return (rooms, doors);
+}
+
+//every room after the first branches off a random earlier one with a free wall
+pub fn generate_tree_map<R: Rng>(
+    num_rooms: u32,
+    num_dead_ends: usize,
+    num_tex: usize,
+    rng: &mut R,
+) -> (Vec<Room>, Vec<Door>) {
+    let mut rooms = vec![Room::new(vec![], rng.gen_range(0..num_tex))];
+    let mut doors = Vec::<Door>::new();
+    while rooms.len() < num_rooms as usize + num_dead_ends {
+        attach_room(&mut rooms, &mut doors, num_tex, rng);
+    }
+    return (rooms, doors);
+}
+
+//adds one room behind a new door on any room that still has a free wall
+fn attach_room<R: Rng>(rooms: &mut Vec<Room>, doors: &mut Vec<Door>, num_tex: usize, rng: &mut R) {
+    let open: Vec<usize> = (0..rooms.len())
+        .filter(|idx| rooms[*idx].doors.len() < 4)
+        .collect();
+    let roomidx = open[rng.gen_range(0..open.len())];
+    let target = rooms.len();
+    let srcroom = &mut rooms[roomidx];
+    let door = gen_valid_door(srcroom, target, doors, rng);
+    doors.push(door);
+    srcroom.doors.push(doors.len() - 1);
+    //make room that points back to that door
+    let back_door = create_bidirectional_door(door, roomidx);
+    doors.push(back_door);
+    let dest_room = Room::new(vec![doors.len() - 1], rng.gen_range(0..num_tex));
+    rooms.push(dest_room);
+}
+
+// Lays the house out on a grid, walking out from room 0. Doors don't have to
+// agree with each other, so a room whose cell is already taken, and anything
+// only reachable through it, is left without a position. Each storey is a
+// grid of its own; stairs lead to the cell next door one storey up or down.
+pub fn place_rooms(rooms: &mut [Room], doors: &[Door]) {
+    for room in rooms.iter_mut() {
+        room.pos = None;
+    }
+    if rooms.is_empty() {
+        return;
+    }
+    rooms[0].pos = Some(Vec2i { x: 0, y: 0 });
+    let mut queue = std::collections::VecDeque::from([0]);
+    while let Some(roomidx) = queue.pop_front() {
+        let pos = rooms[roomidx].pos.unwrap();
+        for dooridx in rooms[roomidx].doors.clone() {
+            let door = doors[dooridx];
+            let cell = pos + get_offset(door.direction);
+            let storey = rooms[door.target].storey;
+            let taken = rooms
+                .iter()
+                .any(|room| room.pos == Some(cell) && room.storey == storey);
+            if rooms[door.target].pos.is_some() || taken {
+                continue;
+            }
+            rooms[door.target].pos = Some(cell);
+            queue.push_back(door.target);
+        }
+    }
+}
+
+//whether walking through the door from room lands in the grid cell next door
+pub fn lines_up(rooms: &[Room], room: usize, door: Door) -> bool {
+    let storey = storey_through(door, rooms[room].storey);
+    return match (rooms[room].pos, rooms[door.target].pos) {
+        (Some(here), Some(there)) => {
+            here + get_offset(door.direction) == there && rooms[door.target].storey == storey
+        }
+        _ => false,
+    };
+}
+
+//the storey you end up on after going through door from storey
+pub fn storey_through(door: Door, storey: usize) -> usize {
+    return match door.stairs {
+        None => storey,
+        Some(Stairs::Up) => storey + 1,
+        Some(Stairs::Down) => storey.saturating_sub(1),
+    };
+}
+
+// Spreads a house over up to floors storeys by turning doors on the way from
+// the chest to the key into staircases up, so the key is always upstairs of
+// the chest when the way there is long enough. Everything else stays on the
+// storey of the room it hangs off. A house of one storey rolls nothing, so it
+// comes out the same as it always did for its seed.
+pub fn stack_floors<R: Rng>(
+    rooms: &mut [Room],
+    doors: &mut [Door],
+    floors: usize,
+    key_room: usize,
+    rng: &mut R,
+) {
+    if floors > 1 {
+        let way = route(rooms, doors, 0, key_room);
+        let climbs = (floors - 1).min(way.len());
+        for pick in rand::seq::index::sample(rng, way.len(), climbs) {
+            let up = way[pick];
+            doors[up].stairs = Some(Stairs::Up);
+            let from = rooms
+                .iter()
+                .position(|room| room.doors.contains(&up))
+                .unwrap();
+            let back = rooms[doors[up].target]
+                .doors
+                .iter()
+                .copied()
+                .find(|back| doors[*back].target == from);
+            if let Some(back) = back {
+                doors[back].stairs = Some(Stairs::Down);
+            }
+        }
+    }
+    //out from the chest, a storey at a time
+    for room in rooms.iter_mut() {
+        room.storey = 0;
+    }
+    let mut seen = vec![false; rooms.len()];
+    let mut stack: Vec<usize> = (0..rooms.len().min(1)).collect();
+    while let Some(roomidx) = stack.pop() {
+        if seen[roomidx] {
+            continue;
+        }
+        seen[roomidx] = true;
+        for dooridx in rooms[roomidx].doors.clone() {
+            let door = doors[dooridx];
+            if !seen[door.target] {
+                rooms[door.target].storey = storey_through(door, rooms[roomidx].storey);
+                stack.push(door.target);
+            }
+        }
+    }
+    place_rooms(rooms, doors);
+}
+
+//the doors walked through on the way from one room to another, if there is a way
+fn route(rooms: &[Room], doors: &[Door], from: usize, to: usize) -> Vec<usize> {
+    let mut came_by: Vec<Option<usize>> = vec![None; rooms.len()];
+    let mut queue = std::collections::VecDeque::from([from]);
+    while let Some(roomidx) = queue.pop_front() {
+        if roomidx == to {
+            break;
+        }
+        for dooridx in rooms[roomidx].doors.iter() {
+            let target = doors[*dooridx].target;
+            if target != from && came_by[target].is_none() {
+                came_by[target] = Some(*dooridx);
+                queue.push_back(target);
+            }
+        }
+    }
+    let mut way = vec![];
+    let mut at = to;
+    while let Some(dooridx) = came_by[at] {
+        way.push(dooridx);
+        at = rooms
+            .iter()
+            .position(|room| room.doors.contains(&dooridx))
+            .unwrap();
+    }
+    way.reverse();
+    return way;
+}
+
+// Joins up to count pairs of rooms that sit side by side on the same storey
+// but have no door between them yet, so there is more than one way round the
+// house. The new doors take a spot both walls have if there is one, and names
+// are dealt again so the new neighbours don't share one. No loops rolls
+// nothing.
+pub fn add_loops<R: Rng>(rooms: &mut [Room], doors: &mut Vec<Door>, count: usize, rng: &mut R) {
+    if count == 0 {
+        return;
+    }
+    let mut pairs = vec![];
+    for roomidx in 0..rooms.len() {
+        let Some(pos) = rooms[roomidx].pos else {
+            continue;
+        };
+        for dir in (0..4).map(get_dir) {
+            let cell = pos + get_offset(dir);
+            let next = rooms
+                .iter()
+                .position(|room| room.pos == Some(cell) && room.storey == rooms[roomidx].storey);
+            let Some(next) = next else {
+                continue;
+            };
+            let door = Door::new(dir, next, get_spawn_dir(dir));
+            let back = create_bidirectional_door(door, roomidx);
+            if roomidx < next
+                && check_valid_door(door, &rooms[roomidx], doors)
+                && check_valid_door(back, &rooms[next], doors)
+            {
+                pairs.push((roomidx, door, back));
+            }
+        }
+    }
+    //each pair has walls of its own, so picking one never spoils another
+    let picks = rand::seq::index::sample(rng, pairs.len(), count.min(pairs.len()));
+    if picks.is_empty() {
+        return;
+    }
+    for pick in picks {
+        let (roomidx, mut door, mut back) = pairs[pick];
+        let slots = door_slots(rooms[roomidx].shape, door.direction);
+        door.offset = slots[rng.gen_range(0..slots.len())];
+        let back_slots = door_slots(rooms[door.target].shape, back.direction);
+        back.offset = if back_slots.contains(&door.offset) {
+            door.offset
+        } else {
+            back_slots[rng.gen_range(0..back_slots.len())]
+        };
+        rooms[roomidx].doors.push(doors.len());
+        doors.push(door);
+        rooms[door.target].doors.push(doors.len());
+        doors.push(back);
+    }
+    identity::assign(rooms, doors, rng);
+}
+
+// Locks up to count doors, and the doors back through them, until the key is
+// found. Only doors off the way from the chest to the key and off the stairs
+// are locked, so the key can always be reached; behind them are side rooms
+// and shortcuts that open up on the way home. No locks rolls nothing.
+pub fn lock_doors<R: Rng>(
+    rooms: &[Room],
+    doors: &mut [Door],
+    count: usize,
+    key_room: usize,
+    rng: &mut R,
+) {
+    if count == 0 {
+        return;
+    }
+    let way = route(rooms, doors, 0, key_room);
+    let mut pairs = vec![];
+    for (roomidx, room) in rooms.iter().enumerate() {
+        for dooridx in room.doors.iter().copied() {
+            let door = doors[dooridx];
+            let back = rooms[door.target]
+                .doors
+                .iter()
+                .copied()
+                .find(|back| doors[*back].target == roomidx);
+            let on_way = way.contains(&dooridx) || back.is_some_and(|back| way.contains(&back));
+            if roomidx < door.target && door.stairs.is_none() && !on_way {
+                pairs.push((dooridx, back));
+            }
+        }
+    }
+    for pick in rand::seq::index::sample(rng, pairs.len(), count.min(pairs.len())) {
+        let (dooridx, back) = pairs[pick];
+        doors[dooridx].locked = true;
+        if let Some(back) = back {
+            doors[back].locked = true;
+        }
+    }
+}
+
+// Gives every room but the first, where the chest stands, a shape, then puts
+// each door somewhere along its wall. A door takes the same spot as the one
+// back if that fits, so the two line up when the rooms sit side by side.
+pub fn shape_rooms<R: Rng>(rooms: &mut [Room], doors: &mut [Door], rng: &mut R) {
+    for room in rooms.iter_mut().skip(1) {
+        room.shape = random_shape(rng);
+    }
+    let mut placed = vec![false; doors.len()];
+    for roomidx in 0..rooms.len() {
+        for dooridx in rooms[roomidx].doors.clone() {
+            let door = doors[dooridx];
+            let slots = door_slots(rooms[roomidx].shape, door.direction);
+            let back = rooms[door.target]
+                .doors
+                .iter()
+                .copied()
+                .find(|back| doors[*back].target == roomidx);
+            let offset = match back {
+                Some(back) if placed[back] && slots.contains(&doors[back].offset) => {
+                    doors[back].offset
+                }
+                _ => slots[rng.gen_range(0..slots.len())],
+            };
+            doors[dooridx].offset = offset;
+            placed[dooridx] = true;
+        }
+    }
+}
+
+//half the rooms are the classic square; the rest are boxes of any size, a third of them Ls
+pub fn random_shape<R: Rng>(rng: &mut R) -> Shape {
+    if rng.gen_bool(0.5) {
+        return Shape::SQUARE;
+    }
+    let sizes = [STEPS / 2, STEPS * 3 / 4, STEPS];
+    let corners = [
+        Corner::NorthEast,
+        Corner::SouthEast,
+        Corner::SouthWest,
+        Corner::NorthWest,
+    ];
+    return Shape {
+        width: sizes[rng.gen_range(0..sizes.len())],
+        depth: sizes[rng.gen_range(0..sizes.len())],
+        notch: if rng.gen_range(0..3) == 0 {
+            Some(corners[rng.gen_range(0..corners.len())])
+        } else {
+            None
+        },
+    };
+}
+
+// Whether a doorway opens straight onto the door back from the room next
+// door: the rooms sit side by side on the grid, both doors stand on the edge
+// of their cells, and at the same spot along it.
+pub fn meets(rooms: &[Room], doors: &[Door], room: usize, door: Door) -> bool {
+    //stairs open onto a flight of steps, not the room at the top
+    if door.stairs.is_some() || !lines_up(rooms, room, door) {
+        return false;
+    }
+    let on_edge = |shape: Shape, door: Door| {
+        wall_at(shape, door.direction, door.offset as f32) * 2.0 == STEPS as f32
+    };
+    return rooms[door.target]
+        .doors
+        .iter()
+        .map(|idx| doors[*idx])
+        .find(|back| back.target == room)
+        .is_some_and(|back| {
+            back.offset == door.offset
+                && on_edge(rooms[room].shape, door)
+                && on_edge(rooms[door.target].shape, back)
+        });
+}
+
+//keep rolling doors until the direction and the target are both new for this room
+pub fn gen_valid_door<R: Rng>(room: &Room, target: usize, doors: &[Door], rng: &mut R) -> Door {
+    let mut door = generate_door(target, rng);
+    while !check_valid_door(door, room, doors) {
+        door = generate_door(target, rng);
+    }
+    return door;
+}
+
+pub fn check_valid_door(door: Door, room: &Room, doors: &[Door]) -> bool {
+    //room is current room
+    let mut check = true;
+    for n in 0..room.doors.len() {
+        if door.direction == doors[room.doors[n]].direction {
+            check = false;
+        }
+        if door.target == doors[room.doors[n]].target {
+            check = false;
+        }
+    }
+    return check;
+}
+
+//return a new door on oppoisite side that points back to the previous room
+pub fn create_bidirectional_door(door: Door, cur_room: usize) -> Door {
+    return Door::new(get_spawn_dir(door.direction), cur_room, door.direction);
+}
+
+//generate a door with random direction and target
+pub fn generate_door<R: Rng>(target: usize, rng: &mut R) -> Door {
+    let direction = get_dir(rng.gen_range(0..4));
+    return Door::new(direction, target, get_spawn_dir(direction));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn every_door_has_a_way_back() {
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let (rooms, doors) = generate_room_map(7, 3, 8, &mut rng);
+            for (roomidx, room) in rooms.iter().enumerate() {
+                for dooridx in room.doors.iter() {
+                    let door = doors[*dooridx];
+                    let back = rooms[door.target]
+                        .doors
+                        .iter()
+                        .map(|idx| doors[*idx])
+                        .find(|back| back.target == roomidx)
+                        .expect("missing back door");
+                    assert_eq!(back.direction, get_spawn_dir(door.direction));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn room_count_includes_dead_ends() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (rooms, doors) = generate_room_map(4, 2, 4, &mut rng);
+        assert_eq!(rooms.len(), 6);
+        assert_eq!(doors.len(), 2 * (rooms.len() - 1));
+        assert!(rooms.iter().all(|room| room.tex_idx < 4));
+    }
+
+    #[test]
+    fn no_wall_holds_two_doors() {
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let (rooms, doors) = generate_room_map(10, 10, 8, &mut rng);
+            for room in rooms.iter() {
+                let mut dirs: Vec<_> = room.doors.iter().map(|d| doors[*d].direction).collect();
+                let len = dirs.len();
+                dirs.sort_by_key(|d| *d as u8);
+                dirs.dedup();
+                assert_eq!(dirs.len(), len);
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_house() {
+        let a = generate_room_map(9, 4, 8, &mut StdRng::seed_from_u64(42));
+        let b = generate_room_map(9, 4, 8, &mut StdRng::seed_from_u64(42));
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn tree_houses_are_connected() {
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let (rooms, doors) = Generator::Tree.generate(9, 3, 8, &mut rng);
+            assert_eq!(rooms.len(), 12);
+            let mut seen = vec![false; rooms.len()];
+            let mut stack = vec![0];
+            while let Some(room) = stack.pop() {
+                if !seen[room] {
+                    seen[room] = true;
+                    stack.extend(rooms[room].doors.iter().map(|d| doors[*d].target));
+                }
+            }
+            assert!(seen.iter().all(|s| *s), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn generators_parse_by_name() {
+        assert_eq!("tree".parse(), Ok(Generator::Tree));
+        assert_eq!("path".parse(), Ok(Generator::Path));
+        assert!("maze".parse::<Generator>().is_err());
+        assert_eq!(Generator::Tree.to_string(), "tree");
+    }
+
+    #[test]
+    fn placed_rooms_never_share_a_cell() {
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            for generator in [Generator::Path, Generator::Tree] {
+                let (rooms, doors) = generator.generate(8, 4, 8, &mut rng);
+                assert_eq!(rooms[0].pos, Some(Vec2i { x: 0, y: 0 }));
+                let cells: Vec<Vec2i> = rooms.iter().filter_map(|room| room.pos).collect();
+                for (i, cell) in cells.iter().enumerate() {
+                    assert!(!cells[i + 1..].contains(cell), "seed {}", seed);
+                }
+                //every placed room but the first was placed from a neighbour
+                for (roomidx, room) in rooms.iter().enumerate().skip(1) {
+                    if room.pos.is_some() {
+                        let back = room.doors.iter().map(|d| doors[*d]);
+                        assert!(back.into_iter().any(|door| lines_up(&rooms, roomidx, door)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn doors_stand_on_their_walls_clear_of_the_ends() {
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            for generator in [Generator::Path, Generator::Tree] {
+                let (rooms, doors) = generator.generate(10, 5, 8, &mut rng);
+                //the chest needs the middle of the first room
+                assert_eq!(rooms[0].shape, Shape::SQUARE);
+                for room in rooms.iter() {
+                    for dooridx in room.doors.iter() {
+                        let door = doors[*dooridx];
+                        let slots = door_slots(room.shape, door.direction);
+                        assert!(slots.contains(&door.offset), "seed {}", seed);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn houses_have_more_than_square_rooms() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let (rooms, _) = Generator::Tree.generate(30, 10, 8, &mut rng);
+        assert!(rooms.iter().any(|room| room.shape.notch.is_some()));
+        assert!(rooms.iter().any(|room| room.shape.width < STEPS));
+        assert!(rooms.iter().any(|room| room.shape == Shape::SQUARE));
+    }
+
+    #[test]
+    fn doorways_meet_only_where_both_doors_line_up() {
+        let mut rooms = vec![Room::new(vec![0], 0), Room::new(vec![1], 0)];
+        let mut doors = vec![
+            Door::new(Direction::North, 1, Direction::South),
+            Door::new(Direction::South, 0, Direction::North),
+        ];
+        place_rooms(&mut rooms, &doors);
+        assert!(meets(&rooms, &doors, 0, doors[0]));
+        doors[1].offset = 2;
+        assert!(!meets(&rooms, &doors, 0, doors[0]));
+        doors[0].offset = 2;
+        assert!(meets(&rooms, &doors, 0, doors[0]));
+        //a shallow room's south wall is in from the edge of its cell
+        rooms[1].shape.depth = STEPS / 2;
+        assert!(!meets(&rooms, &doors, 0, doors[0]));
+    }
+
+    #[test]
+    fn stairs_climb_from_the_chest_to_the_key() {
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            for generator in [Generator::Path, Generator::Tree] {
+                let (mut rooms, mut doors) = generator.generate(8, 4, 8, &mut rng);
+                stack_floors(&mut rooms, &mut doors, 3, 7, &mut rng);
+                let way = route(&rooms, &doors, 0, 7);
+                assert_eq!(rooms[0].storey, 0);
+                assert_eq!(rooms[7].storey, way.len().min(2), "seed {}", seed);
+                for (roomidx, room) in rooms.iter().enumerate() {
+                    for dooridx in room.doors.iter() {
+                        let door = doors[*dooridx];
+                        let back = rooms[door.target]
+                            .doors
+                            .iter()
+                            .map(|idx| doors[*idx])
+                            .find(|back| back.target == roomidx)
+                            .unwrap();
+                        assert_eq!(storey_through(door, room.storey), rooms[door.target].storey);
+                        assert_eq!(storey_through(back, rooms[door.target].storey), room.storey);
+                    }
+                }
+                //a cell can be taken once per storey
+                let cells: Vec<(Vec2i, usize)> = rooms
+                    .iter()
+                    .filter_map(|room| room.pos.map(|pos| (pos, room.storey)))
+                    .collect();
+                for (i, cell) in cells.iter().enumerate() {
+                    assert!(!cells[i + 1..].contains(cell), "seed {}", seed);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn one_storey_houses_have_no_stairs_and_roll_nothing() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let (mut rooms, mut doors) = Generator::Tree.generate(8, 4, 8, &mut rng);
+        let (flat_rooms, flat_doors) = (rooms.clone(), doors.clone());
+        let mut untouched = rng.clone();
+        stack_floors(&mut rooms, &mut doors, 1, 7, &mut rng);
+        assert_eq!((rooms, doors), (flat_rooms, flat_doors));
+        assert_eq!(rng.gen::<u64>(), untouched.gen::<u64>());
+    }
+
+    #[test]
+    fn stairs_never_show_the_room_at_the_top() {
+        let mut rooms = vec![Room::new(vec![0], 0), Room::new(vec![1], 0)];
+        let mut doors = vec![
+            Door::new(Direction::North, 1, Direction::South),
+            Door::new(Direction::South, 0, Direction::North),
+        ];
+        stack_floors(&mut rooms, &mut doors, 2, 1, &mut StdRng::seed_from_u64(0));
+        assert_eq!(doors[0].stairs, Some(Stairs::Up));
+        assert_eq!(doors[1].stairs, Some(Stairs::Down));
+        assert_eq!(rooms[1].storey, 1);
+        assert!(lines_up(&rooms, 0, doors[0]));
+        assert!(!meets(&rooms, &doors, 0, doors[0]));
+    }
+
+    #[test]
+    fn loops_join_rooms_side_by_side_both_ways() {
+        let mut added = 0;
+        for seed in 0..30 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let (mut rooms, mut doors) = Generator::Tree.generate(12, 4, 8, &mut rng);
+            let before = doors.len();
+            add_loops(&mut rooms, &mut doors, 3, &mut rng);
+            assert!(doors.len() <= before + 6);
+            added += doors.len() - before;
+            for (dooridx, door) in doors.iter().copied().enumerate().skip(before) {
+                let roomidx = rooms
+                    .iter()
+                    .position(|room| room.doors.contains(&dooridx))
+                    .unwrap();
+                assert!(lines_up(&rooms, roomidx, door), "seed {}", seed);
+                assert!(door_slots(rooms[roomidx].shape, door.direction).contains(&door.offset));
+            }
+            //still no wall with two doors, and no room with two doors to the same one
+            for room in rooms.iter() {
+                for (i, a) in room.doors.iter().enumerate() {
+                    for b in room.doors[i + 1..].iter() {
+                        assert_ne!(doors[*a].direction, doors[*b].direction);
+                        assert_ne!(doors[*a].target, doors[*b].target);
+                    }
+                }
+            }
+        }
+        //most trees have some rooms side by side that they don't join
+        assert!(added > 30 * 2, "{}", added);
+    }
+
+    #[test]
+    fn locks_keep_the_way_to_the_key_open() {
+        for seed in 0..30 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let (mut rooms, mut doors) = Generator::Tree.generate(8, 6, 8, &mut rng);
+            stack_floors(&mut rooms, &mut doors, 2, 7, &mut rng);
+            lock_doors(&rooms, &mut doors, 3, 7, &mut rng);
+            let locked: Vec<Door> = doors.iter().copied().filter(|door| door.locked).collect();
+            assert!(
+                !locked.is_empty() && locked.len().is_multiple_of(2),
+                "seed {}",
+                seed
+            );
+            assert!(locked.iter().all(|door| door.stairs.is_none()));
+            for dooridx in route(&rooms, &doors, 0, 7) {
+                assert!(!doors[dooridx].locked, "seed {}", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn no_loops_or_locks_roll_nothing() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let (mut rooms, mut doors) = Generator::Tree.generate(8, 4, 8, &mut rng);
+        let (plain_rooms, plain_doors) = (rooms.clone(), doors.clone());
+        let mut untouched = rng.clone();
+        add_loops(&mut rooms, &mut doors, 0, &mut rng);
+        lock_doors(&rooms, &mut doors, 0, 7, &mut rng);
+        assert_eq!((rooms, doors), (plain_rooms, plain_doors));
+        assert_eq!(rng.gen::<u64>(), untouched.gen::<u64>());
+    }
+
+    #[test]
+    fn single_room_house() {
+        let (rooms, doors) = generate_room_map(1, 0, 4, &mut StdRng::seed_from_u64(0));
+        assert_eq!(rooms.len(), 1);
+        assert!(doors.is_empty());
+    }
+}
@@ -0,0 +1,171 @@
+use crate::geometry::get_offset;
+use crate::state::GameState;
+use ultraviolet::Vec2;
+
+//rounds of springs and shoves before the layout is taken as settled
+const SETTLE_STEPS: usize = 200;
+
+// Where each room goes on the minimap, in room widths with room 0 at the
+// origin. Rooms the floor plan placed keep their grid cell. The rest start in
+// a free cell near a neighbour, then are pulled towards the rooms they share
+// doors with and pushed off any room they crowd until they settle. Each
+// storey is its own map, so rooms on different storeys may sit on top of
+// each other; the front end only draws the storey the player is on.
+pub fn layout(state: &GameState) -> Vec<Vec2> {
+    let rooms = &state.rooms;
+    let mut pos: Vec<Option<Vec2>> = rooms
+        .iter()
+        .map(|room| room.pos.map(|cell| Vec2::new(cell.x as f32, cell.y as f32)))
+        .collect();
+    let pinned: Vec<bool> = pos.iter().map(Option::is_some).collect();
+    if rooms.is_empty() {
+        return vec![];
+    }
+    if pos[0].is_none() {
+        pos[0] = Some(Vec2::zero());
+    }
+
+    //out from the placed rooms, one door at a time
+    let mut queue: std::collections::VecDeque<usize> =
+        (0..rooms.len()).filter(|idx| pos[*idx].is_some()).collect();
+    while let Some(roomidx) = queue.pop_front() {
+        let here = pos[roomidx].unwrap();
+        for (_, door) in state.room_doors(roomidx) {
+            if pos[door.target].is_none() {
+                let cell = get_offset(door.direction);
+                let want = here + Vec2::new(cell.x as f32, cell.y as f32);
+                let storey = rooms[door.target].storey;
+                pos[door.target] = Some(free_cell_near(state, &pos, storey, want));
+                queue.push_back(door.target);
+            }
+        }
+    }
+    //rooms off in a part of the house nothing leads to, if there ever are any
+    for idx in 0..pos.len() {
+        if pos[idx].is_none() {
+            pos[idx] = Some(free_cell_near(state, &pos, rooms[idx].storey, Vec2::zero()));
+        }
+    }
+    let mut pos: Vec<Vec2> = pos.into_iter().flatten().collect();
+
+    for _ in 0..SETTLE_STEPS {
+        let mut push = vec![Vec2::zero(); pos.len()];
+        for (roomidx, room) in rooms.iter().enumerate() {
+            for dooridx in room.doors.iter() {
+                let target = state.doors[*dooridx].target;
+                let apart = pos[target] - pos[roomidx];
+                let stretch = apart.mag() - 1.0;
+                if apart.mag() > 1e-4 {
+                    push[roomidx] += apart.normalized() * stretch * 0.05;
+                }
+            }
+            for other in 0..rooms.len() {
+                let apart = pos[roomidx] - pos[other];
+                let gap = apart.mag();
+                let crowds = other != roomidx && rooms[other].storey == room.storey;
+                if crowds && gap < 1.0 {
+                    let away = if gap > 1e-4 {
+                        apart / gap
+                    } else {
+                        Vec2::new(1.0, 0.0)
+                    };
+                    push[roomidx] += away * (1.0 - gap) * 0.5;
+                }
+            }
+        }
+        for (idx, step) in push.into_iter().enumerate() {
+            if !pinned[idx] && idx != 0 {
+                pos[idx] += step;
+            }
+        }
+    }
+    return pos;
+}
+
+//the closest spot on the grid around want that no room on storey has taken yet
+fn free_cell_near(state: &GameState, pos: &[Option<Vec2>], storey: usize, want: Vec2) -> Vec2 {
+    let taken = |at: Vec2| {
+        pos.iter()
+            .zip(state.rooms.iter())
+            .filter(|(_, room)| room.storey == storey)
+            .any(|(other, _)| other.is_some_and(|other| (other - at).mag() < 0.5))
+    };
+    for ring in 0..i32::MAX {
+        for dy in -ring..=ring {
+            for dx in -ring..=ring {
+                let at = want + Vec2::new(dx as f32, dy as f32);
+                if dx.abs().max(dy.abs()) == ring && !taken(at) {
+                    return at;
+                }
+            }
+        }
+    }
+    return want;
+}
+
+//each door walked through this run, once per pair of rooms, lower room first
+pub fn explored_doors(state: &GameState) -> Vec<(usize, usize)> {
+    let mut pairs = vec![];
+    for (roomidx, room) in state.rooms.iter().enumerate() {
+        for dooridx in room.doors.iter() {
+            let target = state.doors[*dooridx].target;
+            let pair = (roomidx.min(target), roomidx.max(target));
+            if state.doors[*dooridx].uses > 0 && !pairs.contains(&pair) {
+                pairs.push(pair);
+            }
+        }
+    }
+    return pairs;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Generator;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn placed_rooms_keep_their_cells() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let state = GameState::generate_with(Generator::Path, 6, 2, 1, 4, &mut rng);
+        let pos = layout(&state);
+        for (room, at) in state.rooms.iter().zip(pos.iter()) {
+            if let Some(cell) = room.pos {
+                assert_eq!(*at, Vec2::new(cell.x as f32, cell.y as f32));
+            }
+        }
+    }
+
+    #[test]
+    fn unplaced_rooms_settle_apart() {
+        let mut unplaced = 0;
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let state = GameState::generate_with(Generator::Tree, 16, 6, 3, 4, &mut rng);
+            unplaced += state.rooms.iter().filter(|room| room.pos.is_none()).count();
+            let pos = layout(&state);
+            for a in 0..pos.len() {
+                for b in a + 1..pos.len() {
+                    if state.rooms[a].storey != state.rooms[b].storey {
+                        continue;
+                    }
+                    let gap = (pos[a] - pos[b]).mag();
+                    assert!(gap > 0.5, "seed {} rooms {} and {}: {}", seed, a, b, gap);
+                }
+            }
+        }
+        //or this test isn't testing anything
+        assert!(unplaced > 0);
+    }
+
+    #[test]
+    fn a_door_counts_once_whichever_way_it_was_walked() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut state = GameState::generate(3, 0, 4, &mut rng);
+        assert!(explored_doors(&state).is_empty());
+        state.doors[0].uses = 1;
+        state.doors[1].uses = 2;
+        assert_eq!(explored_doors(&state), [(0, 1)]);
+    }
+}
@@ -0,0 +1,395 @@
+use crate::collision::{check_item_collisions, in_room_bounds};
+use crate::identity::{landmark_pos, LANDMARK_SIZE};
+use crate::rules::{CHEST_EDGE, CHEST_POS};
+use crate::state::GameState;
+use crate::types::*;
+use ultraviolet::Vec3;
+
+//cells along a step of wall, so a square room is sixteen cells a side
+const CELLS_PER_STEP: usize = 2;
+
+//a box on the floor that walkers go round; y is ignored
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Collider {
+    pub center: Vec3,
+    pub half_x: f32,
+    pub half_z: f32,
+}
+
+impl Collider {
+    pub fn new(center: Vec3, half_x: f32, half_z: f32) -> Self {
+        return Collider {
+            center,
+            half_x,
+            half_z,
+        };
+    }
+}
+
+//what stands on a room's floor: its landmark, and the chest in the first room
+pub fn colliders(state: &GameState, room: usize) -> Vec<Collider> {
+    let shape = state.rooms[room].shape;
+    let mut found = vec![Collider::new(
+        landmark_pos(shape, state.tuning.room_size),
+        LANDMARK_SIZE,
+        LANDMARK_SIZE,
+    )];
+    if room == 0 {
+        found.push(Collider::new(CHEST_POS, CHEST_EDGE.0, CHEST_EDGE.1));
+    }
+    return found;
+}
+
+// A room's floor cut into square cells, each one open or not. A cell is open
+// when a walker of the grid's radius can stand anywhere in it: the cell grown
+// by the radius is all floor, and clear of every collider. Everything a walker does in a room goes through
+// one of these, so a new piece of furniture only has to be a collider.
+#[derive(Clone, PartialEq, Debug)]
+pub struct NavGrid {
+    xs: usize, //cells across, along x
+    zs: usize, //and along z
+    cell: f32, //width of a cell
+    half_x: f32,
+    half_z: f32,
+    open: Vec<bool>, //by row along z, then along x
+}
+
+impl NavGrid {
+    pub fn new(shape: Shape, room_size: f32, colliders: &[Collider], radius: f32) -> Self {
+        let cell = room_size / (STEPS as usize * CELLS_PER_STEP) as f32;
+        let (xs, zs) = (
+            shape.width as usize * CELLS_PER_STEP,
+            shape.depth as usize * CELLS_PER_STEP,
+        );
+        let mut grid = NavGrid {
+            xs,
+            zs,
+            cell,
+            half_x: xs as f32 * cell / 2.0,
+            half_z: zs as f32 * cell / 2.0,
+            open: vec![false; xs * zs],
+        };
+        let reach = cell / 2.0 + radius;
+        for z in 0..zs {
+            for x in 0..xs {
+                let middle = grid.middle((x, z));
+                let on_floor = [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)]
+                    .iter()
+                    .all(|(dx, dz)| {
+                        let corner = middle + Vec3::new(dx * reach, 0.0, dz * reach);
+                        in_room_bounds(corner, shape, room_size)
+                    });
+                let clear = colliders.iter().all(|collider| {
+                    !check_item_collisions(
+                        middle,
+                        collider.center,
+                        collider.half_x + reach,
+                        collider.half_z + reach,
+                    )
+                });
+                grid.open[z * xs + x] = on_floor && clear;
+            }
+        }
+        return grid;
+    }
+
+    //the grid for one of state's rooms, for walkers of the given radius
+    pub fn for_room(state: &GameState, room: usize, radius: f32) -> Self {
+        return NavGrid::new(
+            state.rooms[room].shape,
+            state.tuning.room_size,
+            &colliders(state, room),
+            radius,
+        );
+    }
+
+    //the cell pos is over, or the nearest one along the edge if it is off the grid
+    pub fn cell_at(&self, pos: Vec3) -> (usize, usize) {
+        let x = ((pos.x + self.half_x) / self.cell).floor();
+        let z = ((pos.z + self.half_z) / self.cell).floor();
+        return (
+            (x.max(0.0) as usize).min(self.xs - 1),
+            (z.max(0.0) as usize).min(self.zs - 1),
+        );
+    }
+
+    pub fn middle(&self, (x, z): (usize, usize)) -> Vec3 {
+        return Vec3::new(
+            (x as f32 + 0.5) * self.cell - self.half_x,
+            0.0,
+            (z as f32 + 0.5) * self.cell - self.half_z,
+        );
+    }
+
+    pub fn is_open(&self, (x, z): (usize, usize)) -> bool {
+        return self.open[z * self.xs + x];
+    }
+
+    // Whether a walker can go straight from one point to the other. The cells
+    // at either end do not count, so walkers can leave from against a wall
+    // and head for a door in one.
+    pub fn clear_line(&self, from: Vec3, to: Vec3) -> bool {
+        let ends = [self.cell_at(from), self.cell_at(to)];
+        let gap = flat(to - from);
+        let samples = (gap.mag() / (self.cell / 4.0)).ceil() as usize;
+        return (1..samples).all(|i| {
+            let cell = self.cell_at(from + gap * (i as f32 / samples as f32));
+            ends.contains(&cell) || self.is_open(cell)
+        });
+    }
+
+    //the open cell nearest pos
+    fn nearest_open(&self, pos: Vec3) -> Option<(usize, usize)> {
+        return (0..self.zs)
+            .flat_map(|z| (0..self.xs).map(move |x| (x, z)))
+            .filter(|cell| self.is_open(*cell))
+            .min_by(|a, b| {
+                let a = (self.middle(*a) - pos).mag_sq();
+                let b = (self.middle(*b) - pos).mag_sq();
+                a.total_cmp(&b)
+            });
+    }
+
+    // Points to walk through in turn to get from one point to the other, the
+    // last one being `to` itself, or None if the furniture walls it off. An
+    // A* search over open cells, eight ways round, without cutting the corner
+    // of a closed cell; from and to start from the open cells nearest them.
+    pub fn path(&self, from: Vec3, to: Vec3) -> Option<Vec<Vec3>> {
+        let start = self.nearest_open(from)?;
+        let goal = self.nearest_open(to)?;
+        let index = |(x, z): (usize, usize)| z * self.xs + x;
+        //octile distance, which never overestimates eight way moves
+        let guess = |(x, z): (usize, usize)| {
+            let dx = (x as f32 - goal.0 as f32).abs();
+            let dz = (z as f32 - goal.1 as f32).abs();
+            return dx.max(dz) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dz);
+        };
+        let mut cost = vec![f32::INFINITY; self.open.len()];
+        let mut came: Vec<Option<(usize, usize)>> = vec![None; self.open.len()];
+        let mut done = vec![false; self.open.len()];
+        let mut frontier = vec![start];
+        cost[index(start)] = 0.0;
+        while let Some(at) = frontier
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                let a = cost[index(**a)] + guess(**a);
+                let b = cost[index(**b)] + guess(**b);
+                a.total_cmp(&b)
+            })
+            .map(|(i, _)| i)
+        {
+            let cell = frontier.swap_remove(at);
+            if cell == goal {
+                break;
+            }
+            if done[index(cell)] {
+                continue;
+            }
+            done[index(cell)] = true;
+            for dz in -1i32..=1 {
+                for dx in -1i32..=1 {
+                    let (x, z) = (cell.0 as i32 + dx, cell.1 as i32 + dz);
+                    if (dx, dz) == (0, 0)
+                        || x < 0
+                        || z < 0
+                        || x >= self.xs as i32
+                        || z >= self.zs as i32
+                    {
+                        continue;
+                    }
+                    let next = (x as usize, z as usize);
+                    let side_x = (x as usize, cell.1);
+                    let side_z = (cell.0, z as usize);
+                    if !self.is_open(next) || !self.is_open(side_x) || !self.is_open(side_z) {
+                        continue;
+                    }
+                    let far = cost[index(cell)] + ((dx * dx + dz * dz) as f32).sqrt();
+                    if far < cost[index(next)] {
+                        cost[index(next)] = far;
+                        came[index(next)] = Some(cell);
+                        frontier.push(next);
+                    }
+                }
+            }
+        }
+        if cost[index(goal)].is_infinite() {
+            return None;
+        }
+        let mut points = vec![to];
+        let mut at = goal;
+        while at != start {
+            points.push(self.middle(at));
+            at = came[index(at)].unwrap();
+        }
+        points.push(self.middle(start));
+        points.reverse();
+        return Some(points);
+    }
+
+    // Where to head for next on the way from one point to the other: the
+    // furthest point along the path that can be walked to in a straight
+    // line, and so the other point itself whenever nothing is in between.
+    // Once a walker is as close as the open cells get, say to a door tucked
+    // in behind a landmark, it squeezes the rest of the way in a straight line.
+    pub fn waypoint(&self, from: Vec3, to: Vec3) -> Vec3 {
+        if self.clear_line(from, to) {
+            return to;
+        }
+        let Some(points) = self.path(from, to) else {
+            //nothing gets through, so at least get closer
+            return to;
+        };
+        let last = points[points.len() - 2];
+        if flat(to - from).mag() <= flat(to - last).mag() {
+            return to;
+        }
+        return points
+            .iter()
+            .rev()
+            .find(|point| self.clear_line(from, **point))
+            .copied()
+            .unwrap_or(points[0]);
+    }
+
+    //a step of at most speed across the floor towards the next waypoint, landing on it exactly
+    pub fn steer(&self, pos: Vec3, to: Vec3, speed: f32) -> Vec3 {
+        let target = self.waypoint(pos, to);
+        let gap = flat(target - pos);
+        if gap.mag() <= speed {
+            return Vec3::new(target.x, pos.y, target.z);
+        }
+        return pos + gap.normalized() * speed;
+    }
+}
+
+fn flat(v: Vec3) -> Vec3 {
+    return Vec3::new(v.x, 0.0, v.z);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: f32 = 60.0;
+    const RADIUS: f32 = 2.5;
+
+    fn square() -> Shape {
+        return Shape::SQUARE;
+    }
+
+    fn ell() -> Shape {
+        return Shape {
+            width: 8,
+            depth: 8,
+            notch: Some(Corner::NorthEast),
+        };
+    }
+
+    //walks from one point to the other and gives back every position on the way
+    fn walk(grid: &NavGrid, from: Vec3, to: Vec3) -> Vec<Vec3> {
+        let mut pos = from;
+        let mut trail = vec![pos];
+        for _ in 0..1000 {
+            if pos == to {
+                return trail;
+            }
+            pos = grid.steer(pos, to, 0.5);
+            trail.push(pos);
+        }
+        panic!("never got from {:?} to {:?}", from, to);
+    }
+
+    #[test]
+    fn an_empty_room_is_crossed_in_a_straight_line() {
+        let grid = NavGrid::new(square(), SIZE, &[], RADIUS);
+        let (from, to) = (Vec3::new(-20.0, 0.0, -10.0), Vec3::new(20.0, 0.0, 15.0));
+        assert_eq!(grid.waypoint(from, to), to);
+        let trail = walk(&grid, from, to);
+        assert_eq!(trail.len() - 1, ((to - from).mag() / 0.5).ceil() as usize);
+    }
+
+    #[test]
+    fn cells_against_the_walls_are_closed() {
+        let grid = NavGrid::new(square(), SIZE, &[], RADIUS);
+        assert!(!grid.is_open(grid.cell_at(Vec3::new(29.0, 0.0, 0.0))));
+        assert!(!grid.is_open(grid.cell_at(Vec3::new(0.0, 0.0, -29.0))));
+        assert!(grid.is_open(grid.cell_at(Vec3::new(25.0, 0.0, 0.0))));
+        //so is everything off the grid, which is clamped to its edge
+        assert_eq!(grid.cell_at(Vec3::new(100.0, 0.0, -100.0)), (15, 0));
+    }
+
+    #[test]
+    fn walkers_go_round_the_chest_in_the_first_room() {
+        let chest = Collider::new(CHEST_POS, CHEST_EDGE.0, CHEST_EDGE.1);
+        let grid = NavGrid::new(square(), SIZE, &[chest], RADIUS);
+        let (from, to) = (Vec3::new(-20.0, 0.0, 0.0), Vec3::new(20.0, 0.0, 0.0));
+        assert_ne!(grid.waypoint(from, to), to);
+        for pos in walk(&grid, from, to) {
+            assert!(!check_item_collisions(
+                pos,
+                CHEST_POS,
+                CHEST_EDGE.0,
+                CHEST_EDGE.1
+            ));
+        }
+    }
+
+    #[test]
+    fn walkers_go_round_the_inside_corner_of_an_l() {
+        let grid = NavGrid::new(ell(), SIZE, &[], RADIUS);
+        //from the north west arm to the south east one, past the notch in the north east
+        let (from, to) = (Vec3::new(-20.0, 0.0, 20.0), Vec3::new(20.0, 0.0, -20.0));
+        let trail = walk(&grid, from, to);
+        for pos in trail.iter() {
+            assert!(in_room_bounds(*pos, ell(), SIZE), "{:?}", pos);
+        }
+        //the straight line runs along the notch's edge, too close for a walker
+        assert!(!grid.clear_line(from, to));
+        for pos in trail.iter() {
+            assert!(pos.x < -RADIUS / 2.0 || pos.z < -RADIUS / 2.0, "{:?}", pos);
+        }
+    }
+
+    #[test]
+    fn walkers_go_round_a_landmark() {
+        let landmark = Collider::new(landmark_pos(square(), SIZE), LANDMARK_SIZE, LANDMARK_SIZE);
+        let grid = NavGrid::new(square(), SIZE, &[landmark], RADIUS);
+        //from one side of the landmark in the north east corner to the other
+        let (from, to) = (Vec3::new(5.0, 0.0, 20.0), Vec3::new(20.0, 0.0, 5.0));
+        assert!(!grid.clear_line(from, to));
+        let trail = walk(&grid, from, to);
+        for pos in trail.iter() {
+            let inside = check_item_collisions(*pos, landmark.center, LANDMARK_SIZE, LANDMARK_SIZE);
+            assert!(!inside, "{:?}", pos);
+        }
+    }
+
+    #[test]
+    fn a_door_tucked_in_behind_a_landmark_is_still_reached() {
+        let shape = Shape {
+            width: 8,
+            depth: 4,
+            notch: None,
+        };
+        let landmark = Collider::new(landmark_pos(shape, SIZE), LANDMARK_SIZE, LANDMARK_SIZE);
+        let grid = NavGrid::new(shape, SIZE, &[landmark], RADIUS);
+        //no open cell is anywhere near the east wall by the landmark
+        let (from, to) = (Vec3::new(-20.0, 0.0, 9.0), Vec3::new(30.0, 0.0, 9.0));
+        assert!(!grid.is_open(grid.cell_at(Vec3::new(27.0, 0.0, 9.0))));
+        let trail = walk(&grid, from, to);
+        assert_eq!(*trail.last().unwrap(), to);
+    }
+
+    #[test]
+    fn a_walled_off_goal_has_no_path() {
+        //a wall of furniture right across the room
+        let wall = Collider::new(Vec3::zero(), 2.0, 40.0);
+        let grid = NavGrid::new(square(), SIZE, &[wall], RADIUS);
+        let (from, to) = (Vec3::new(-20.0, 0.0, 0.0), Vec3::new(20.0, 0.0, 0.0));
+        assert_eq!(grid.path(from, to), None);
+        assert!(grid.path(from, Vec3::new(-20.0, 0.0, 20.0)).is_some());
+        //and the walker at least heads for it
+        assert_eq!(grid.waypoint(from, to), to);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::config::{Config, GhostRules};
+use crate::map::Generator;
+use crate::replay::Setup;
+use crate::state::GameplayState;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "usage: scene3d [--config FILE] [--seed N] [--rooms N] [--difficulty N] \
+                         [--dead-ends N] [--floors N] [--generator path|tree] [--ghosts N] \
+                         [--play] [--time-attack] [--daily] [--endless] [--level N] [--mute] \
+                         [--replay FILE] [--record FILE] [--times FILE] [--history FILE] \
+                         [--progress FILE] [--dev]
+
+  --config FILE     tuning and house settings (default config.toml, if there is one)
+  --seed N          build the same house every time
+  --rooms N         rooms on the path to the key in the first house
+  --difficulty N    rooms added to the path on every restart
+  --dead-ends N     rooms off the path in the first house
+  --floors N        storeys the house is spread over, joined by staircases
+  --generator NAME  path: one corridor of rooms; tree: rooms branch anywhere
+  --ghosts N        ghosts roaming every house
+  --play            skip the title and instruction screens
+  --time-attack     skip them and play against the clock
+  --daily           play today's house against the clock, the same for everyone
+  --endless         play level after level, carrying on from the last session
+  --level N         start the endless game on level N instead
+  --mute            no music
+  --replay FILE     play a recording back instead of reading the keyboard
+  --record FILE     where recordings are saved (default last_run.replay)
+  --times FILE      where best time-attack times are kept (default best_times.txt)
+  --history FILE    where daily houses are kept (default daily_history.txt)
+  --progress FILE   where the endless game is kept (default progress.txt)
+  --dev             reload room textures and the config file when they change
+
+The house options override the [house] section of the config file, and
+--daily overrides both. --endless takes its houses from the [levels] section.";
+
+// What the game binary was asked to do. Everything that shapes the house ends
+// up in a replay `Setup`, so a recorded session can be rebuilt from its file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub config: Option<PathBuf>,
+    pub seed: Option<u64>, //None picks one at random
+    pub rooms: Option<usize>,
+    pub difficulty: Option<usize>,
+    pub dead_ends: Option<usize>,
+    pub floors: Option<usize>,
+    pub generator: Option<Generator>,
+    pub ghosts: Option<usize>,
+    pub skip_menus: bool,
+    pub time_attack: bool,
+    pub daily: bool,
+    pub endless: bool,
+    pub level: Option<usize>, //None carries on from the progress file
+    pub mute: bool,
+    pub dev: bool,
+    pub replay: Option<PathBuf>,
+    pub record: PathBuf,
+    pub times: PathBuf,
+    pub history: PathBuf,
+    pub progress: PathBuf,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        return Options {
+            config: None,
+            seed: None,
+            rooms: None,
+            difficulty: None,
+            dead_ends: None,
+            floors: None,
+            generator: None,
+            ghosts: None,
+            skip_menus: false,
+            time_attack: false,
+            daily: false,
+            endless: false,
+            level: None,
+            mute: false,
+            dev: false,
+            replay: None,
+            record: PathBuf::from("last_run.replay"),
+            times: PathBuf::from("best_times.txt"),
+            history: PathBuf::from("daily_history.txt"),
+            progress: PathBuf::from("progress.txt"),
+        };
+    }
+}
+
+impl Options {
+    //args without the program name; Err holds the message to print
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--help" | "-h" => return Err(USAGE.to_string()),
+                "--play" => {
+                    options.skip_menus = true;
+                    continue;
+                }
+                "--time-attack" => {
+                    options.time_attack = true;
+                    continue;
+                }
+                "--daily" => {
+                    options.daily = true;
+                    continue;
+                }
+                "--endless" => {
+                    options.endless = true;
+                    continue;
+                }
+                "--mute" => {
+                    options.mute = true;
+                    continue;
+                }
+                "--dev" => {
+                    options.dev = true;
+                    continue;
+                }
+                _ => {}
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} needs a value\n{}", flag, USAGE))?;
+            let bad = || format!("bad value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--config" => options.config = Some(PathBuf::from(value)),
+                "--seed" => options.seed = Some(value.parse().map_err(|_| bad())?),
+                "--rooms" => options.rooms = Some(value.parse().map_err(|_| bad())?),
+                "--difficulty" => options.difficulty = Some(value.parse().map_err(|_| bad())?),
+                "--dead-ends" => options.dead_ends = Some(value.parse().map_err(|_| bad())?),
+                "--floors" => options.floors = Some(value.parse().map_err(|_| bad())?),
+                "--generator" => options.generator = Some(value.parse()?),
+                "--ghosts" => options.ghosts = Some(value.parse().map_err(|_| bad())?),
+                "--replay" => options.replay = Some(PathBuf::from(value)),
+                "--record" => options.record = PathBuf::from(value),
+                "--times" => options.times = PathBuf::from(value),
+                "--history" => options.history = PathBuf::from(value),
+                "--progress" => options.progress = PathBuf::from(value),
+                "--level" => options.level = Some(value.parse().map_err(|_| bad())?),
+                _ => return Err(format!("unknown option {}\n{}", flag, USAGE)),
+            }
+        }
+        if options.rooms == Some(0) {
+            return Err("--rooms must be at least 1".to_string());
+        }
+        if options.floors == Some(0) {
+            return Err("--floors must be at least 1".to_string());
+        }
+        if options.level == Some(0) {
+            return Err("--level must be at least 1".to_string());
+        }
+        let shaped = options.seed.is_some()
+            || options.rooms.is_some()
+            || options.dead_ends.is_some()
+            || options.floors.is_some()
+            || options.generator.is_some()
+            || options.ghosts.is_some();
+        if options.daily && shaped {
+            return Err(
+                "--daily builds the same house for everyone, so it takes no house options"
+                    .to_string(),
+            );
+        }
+        let levelled = options.rooms.is_some()
+            || options.difficulty.is_some()
+            || options.dead_ends.is_some()
+            || options.ghosts.is_some();
+        if options.endless && (options.daily || levelled) {
+            return Err(
+                "--endless takes its houses from the [levels] section, so it takes no \
+                 --daily, --rooms, --difficulty, --dead-ends or --ghosts"
+                    .to_string(),
+            );
+        }
+        if options.level.is_some() && !options.endless {
+            return Err("--level is for the endless game, with --endless".to_string());
+        }
+        return Ok(options);
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        return self
+            .config
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("config.toml"));
+    }
+
+    //the file named with --config must exist; the default one may not
+    pub fn load_config(&self) -> Result<Config, String> {
+        let path = self.config_path();
+        let loaded = match &self.config {
+            Some(_) => Config::load(&path),
+            None => Config::load_or_default(&path),
+        };
+        return loaded.map_err(|err| format!("{}: {}", path.display(), err));
+    }
+
+    // The first house, with the key in the last room of the path. The endless
+    // game starts on --level, or level 1 if the front end hasn't filled that in
+    // from the progress file.
+    pub fn setup(&self, config: &Config, seed: u64, num_tex: usize) -> Setup {
+        let house = &config.house;
+        let level = self.level.unwrap_or(1);
+        let stage = config.levels.stage(level);
+        let (rooms, dead_ends) = if self.endless {
+            (stage.rooms, stage.dead_ends)
+        } else {
+            (
+                self.rooms.unwrap_or(house.rooms),
+                self.dead_ends.unwrap_or(house.dead_ends),
+            )
+        };
+        return Setup {
+            seed: self.seed.unwrap_or(seed),
+            rooms,
+            dead_ends,
+            floors: self.floors.unwrap_or(house.floors),
+            difficulty: self.difficulty.unwrap_or(house.difficulty),
+            generator: self.generator.unwrap_or(house.generator),
+            num_tex,
+            key_index: rooms - 1,
+            start: if self.time_attack {
+                GameplayState::TimeAttack
+            } else if self.skip_menus {
+                GameplayState::Play
+            } else {
+                GameplayState::Mainscreen
+            },
+            tuning: config.tuning,
+            ghosts: GhostRules {
+                count: self.ghosts.unwrap_or(config.ghosts.count),
+                ..config.ghosts
+            },
+            levels: self.endless.then_some(config.levels),
+            level,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        return Options::parse(args.split_whitespace().map(String::from));
+    }
+
+    #[test]
+    fn no_arguments_is_the_classic_game() {
+        let options = parse("").unwrap();
+        assert_eq!(options, Options::default());
+        let setup = options.setup(&Config::default(), 5, 8);
+        assert_eq!(setup.key_index, 3);
+        assert_eq!(setup.start, GameplayState::Mainscreen);
+    }
+
+    #[test]
+    fn every_option_lands_in_the_setup() {
+        let options = parse(
+            "--seed 9 --rooms 6 --difficulty 1 --dead-ends 0 --floors 2 --generator tree \
+             --ghosts 1 --play --mute --dev",
+        )
+        .unwrap();
+        assert!(options.mute && options.dev);
+        let setup = options.setup(&Config::default(), 5, 8);
+        assert_eq!(setup.seed, 9);
+        assert_eq!(setup.key_index, 5);
+        assert_eq!(setup.start, GameplayState::Play);
+
+        let (state, _) = setup.build();
+        assert_eq!(state.max_rooms, 6);
+        assert_eq!(state.rooms.len(), 6);
+        assert_eq!(state.difficulty, 1);
+        assert_eq!(state.generator, Generator::Tree);
+        assert_eq!(state.floors, 2);
+        assert_eq!(state.ghosts.len(), 1);
+    }
+
+    #[test]
+    fn time_attacks_start_on_the_clock() {
+        let options = parse("--time-attack --times mine.txt").unwrap();
+        assert_eq!(options.times, PathBuf::from("mine.txt"));
+        let setup = options.setup(&Config::default(), 5, 8);
+        assert_eq!(setup.start, GameplayState::TimeAttack);
+        let (state, _) = setup.build();
+        assert_eq!(state.clock, Some(0));
+    }
+
+    #[test]
+    fn the_daily_house_is_nobody_elses_to_shape() {
+        let options = parse("--daily --history days.txt --mute").unwrap();
+        assert!(options.daily);
+        assert_eq!(options.history, PathBuf::from("days.txt"));
+        let err = parse("--daily --rooms 9").unwrap_err();
+        assert!(err.starts_with("--daily builds the same house"), "{}", err);
+    }
+
+    #[test]
+    fn the_endless_game_builds_the_house_for_its_level() {
+        let options = parse("--endless --level 7 --progress mine.txt --floors 2").unwrap();
+        assert_eq!(options.progress, PathBuf::from("mine.txt"));
+        let config = Config::default();
+        let stage = config.levels.stage(7);
+        let setup = options.setup(&config, 5, 8);
+        assert_eq!(
+            (setup.rooms, setup.dead_ends),
+            (stage.rooms, stage.dead_ends)
+        );
+        assert_eq!((setup.levels, setup.level), (Some(config.levels), 7));
+        let (state, _) = setup.build();
+        assert_eq!((state.level, state.floors), (7, 2));
+        assert_eq!(state.ghosts.len(), stage.ghosts);
+
+        assert_eq!(parse("--endless").unwrap().setup(&config, 5, 8).level, 1);
+        assert!(parse("--endless --rooms 9")
+            .unwrap_err()
+            .starts_with("--endless takes"));
+        assert!(parse("--endless --daily").is_err());
+        assert!(parse("--level 3").is_err());
+        assert!(parse("--endless --level 0").is_err());
+    }
+
+    #[test]
+    fn the_config_file_fills_in_what_the_command_line_leaves_out() {
+        let config =
+            Config::parse("[tuning]\nspeed = 0.25\n[house]\nrooms = 7\ndead_ends = 5\n").unwrap();
+        let setup = parse("--dead-ends 1").unwrap().setup(&config, 5, 8);
+        assert_eq!(setup.rooms, 7);
+        assert_eq!(setup.key_index, 6);
+        assert_eq!(setup.dead_ends, 1);
+        assert_eq!(setup.tuning.speed, 0.25);
+    }
+
+    #[test]
+    fn a_named_config_file_has_to_exist() {
+        let err = parse("--config nowhere.toml")
+            .unwrap()
+            .load_config()
+            .unwrap_err();
+        assert!(err.starts_with("nowhere.toml: "), "{}", err);
+    }
+
+    #[test]
+    fn bad_arguments_say_what_is_wrong() {
+        assert_eq!(parse("--rooms x").unwrap_err(), "bad value for --rooms: x");
+        assert_eq!(
+            parse("--rooms 0").unwrap_err(),
+            "--rooms must be at least 1"
+        );
+        assert!(parse("--seed")
+            .unwrap_err()
+            .starts_with("--seed needs a value"));
+        assert!(parse("--fast 1").unwrap_err().starts_with("unknown option"));
+        assert!(parse("--generator maze")
+            .unwrap_err()
+            .contains("unknown generator"));
+    }
+}
@@ -0,0 +1,87 @@
+use crate::save::SaveFile;
+
+// How far the endless game has got, kept in a local file between sessions:
+// the level the next session starts on and the highest level ever cleared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub level: usize,
+    pub best: usize, //0 until the first level is cleared
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        return Progress { level: 1, best: 0 };
+    }
+}
+
+impl Progress {
+    //a finished level: cleared moves on to the next, anything else plays it again
+    pub fn record(&mut self, level: usize, cleared: bool) {
+        if cleared {
+            self.level = level + 1;
+            self.best = self.best.max(level);
+        } else {
+            self.level = level;
+        }
+    }
+}
+
+//either line may be missing and keeps its default
+impl SaveFile for Progress {
+    const HEADER: &'static str = "scene3d endless progress";
+    const KIND: &'static str = "an endless progress file";
+
+    fn records(&self) -> Vec<String> {
+        return vec![
+            format!("level {}", self.level),
+            format!("best {}", self.best),
+        ];
+    }
+
+    fn read(&mut self, words: &[&str]) -> Option<()> {
+        if words.len() != 2 {
+            return None;
+        }
+        let value: usize = words[1].parse().ok()?;
+        match words[0] {
+            "level" if value >= 1 => self.level = value,
+            "best" => self.best = value,
+            _ => return None,
+        }
+        return Some(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleared_levels_move_on_and_lost_ones_come_round_again() {
+        let mut progress = Progress::default();
+        progress.record(1, true);
+        progress.record(2, true);
+        assert_eq!(progress, Progress { level: 3, best: 2 });
+        progress.record(3, false);
+        assert_eq!(progress, Progress { level: 3, best: 2 });
+        //starting over lower never lowers the best
+        progress.record(1, true);
+        assert_eq!(progress, Progress { level: 2, best: 2 });
+    }
+
+    #[test]
+    fn progress_survives_the_round_trip_to_text() {
+        let progress = Progress {
+            level: 12,
+            best: 14,
+        };
+        assert_eq!(Progress::parse(&progress.to_text()).unwrap(), progress);
+        assert_eq!(
+            Progress::parse("best 3\n").unwrap(),
+            Progress { level: 1, best: 3 }
+        );
+        assert!(Progress::parse("level 0\n").is_err());
+        assert!(Progress::parse("level two\n").is_err());
+        assert!(Progress::parse("lives 3\n").is_err());
+    }
+}
@@ -0,0 +1,782 @@
+use crate::config::{Curve, GhostRules, Levels, Tuning};
+use crate::map::*;
+use crate::rules::{self, Controls};
+use crate::save;
+use crate::sim::{ScriptError, Scripted};
+use crate::state::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fmt;
+use std::path::Path;
+
+// Everything needed to rebuild the first house of a session. The same seeded
+// rng that builds it is then handed to every `rules::update`, so restarts come
+// out the same too.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Setup {
+    pub seed: u64,
+    pub rooms: usize,
+    pub dead_ends: usize,
+    pub floors: usize,
+    pub difficulty: usize,
+    pub generator: Generator,
+    pub num_tex: usize,
+    pub key_index: usize,
+    pub start: GameplayState,
+    pub tuning: Tuning,
+    pub ghosts: GhostRules,
+    pub levels: Option<Levels>, //the endless game, whose level overrides the ghost count
+    pub level: usize,
+}
+
+impl Setup {
+    pub fn build(&self) -> (GameState, StdRng) {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let (mut room_list, mut door_list) =
+            self.generator
+                .generate(self.rooms as u32, self.dead_ends, self.num_tex, &mut rng);
+        stack_floors(
+            &mut room_list,
+            &mut door_list,
+            self.floors,
+            self.key_index,
+            &mut rng,
+        );
+        let mut state = GameState::new(
+            room_list,
+            door_list,
+            self.rooms,
+            self.key_index,
+            self.num_tex,
+            self.start,
+        );
+        state.dead_ends = self.dead_ends;
+        state.floors = self.floors;
+        state.difficulty = self.difficulty;
+        state.generator = self.generator;
+        state.tuning = self.tuning;
+        match self.levels {
+            Some(levels) => state.enter_level(levels, self.level, self.ghosts, &mut rng),
+            None => state.haunt(self.ghosts, &mut rng),
+        }
+        return (state, rng);
+    }
+}
+
+// A few fields a person can read plus a hash of the whole state, so a replay
+// that drifts says where it ended up and one that doesn't can't hide anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub ticks: usize,
+    pub room: usize,
+    pub has_key: bool,
+    pub finished: bool,
+    pub x: u32, //f32 bits, so the comparison is exact
+    pub z: u32,
+    pub hash: u64,
+}
+
+impl Snapshot {
+    pub fn of(state: &GameState, ticks: usize) -> Self {
+        //FNV-1a over the Debug output; f32 Debug round-trips exactly
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in format!("{:?}", state).bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        return Snapshot {
+            ticks,
+            room: state.current_room,
+            has_key: state.has_key,
+            finished: state.is_finished,
+            x: state.player.pos.x.to_bits(),
+            z: state.player.pos.z.to_bits(),
+            hash,
+        };
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "tick {} room {} key {} finished {} at ({}, {}) hash {:016x}",
+            self.ticks,
+            self.room,
+            self.has_key,
+            self.finished,
+            f32::from_bits(self.x),
+            f32::from_bits(self.z),
+            self.hash
+        );
+    }
+}
+
+// A `--dev` reload of config.toml partway through a session. It changes the
+// settings the house is played and rebuilt with, so a recording has to say
+// which tick it came in on for a replay to change them at the same point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Retune {
+    pub tuning: Tuning,
+    pub floors: usize,
+    pub difficulty: usize,
+    pub generator: Generator,
+    pub ghosts: GhostRules,
+    pub levels: Option<Levels>, //only the endless game has curves to change
+}
+
+impl Retune {
+    pub fn apply(&self, state: &mut GameState) {
+        state.tuning = self.tuning;
+        state.floors = self.floors;
+        state.difficulty = self.difficulty;
+        state.generator = self.generator;
+        state.ghost_rules = self.ghosts;
+        if state.levels.is_some() && self.levels.is_some() {
+            state.levels = self.levels;
+        }
+    }
+}
+
+//the front end pushes every tick's input; saving can happen at any point
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    setup: Setup,
+    frames: Vec<Controls>,
+    retunes: Vec<(usize, Retune)>,
+}
+
+impl Recorder {
+    pub fn new(setup: Setup) -> Self {
+        return Recorder {
+            setup,
+            frames: vec![],
+            retunes: vec![],
+        };
+    }
+
+    pub fn push(&mut self, controls: Controls) {
+        self.frames.push(controls);
+    }
+
+    //ticks recorded so far, which is also the tick the next input is for
+    pub fn ticks(&self) -> usize {
+        return self.frames.len();
+    }
+
+    //applies to the state before the next input pushed
+    pub fn retune(&mut self, retune: Retune) {
+        self.retunes.push((self.frames.len(), retune));
+    }
+
+    //the state passed in must be the one the recorded inputs led to
+    pub fn finish(&self, state: &GameState) -> Recording {
+        return Recording {
+            setup: self.setup,
+            frames: self.frames.clone(),
+            retunes: self.retunes.clone(),
+            end: Snapshot::of(state, self.frames.len()),
+        };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recording {
+    pub setup: Setup,
+    pub frames: Vec<Controls>,
+    pub retunes: Vec<(usize, Retune)>, //each with the tick it came in before
+    pub end: Snapshot,
+}
+
+impl Recording {
+    pub fn to_text(&self) -> String {
+        let setup = &self.setup;
+        let end = &self.end;
+        let mut text = String::from("# scene3d replay\n");
+        text.push_str(&format!("seed {}\n", setup.seed));
+        text.push_str(&format!("rooms {}\n", setup.rooms));
+        text.push_str(&format!("dead_ends {}\n", setup.dead_ends));
+        text.push_str(&format!("floors {}\n", setup.floors));
+        text.push_str(&format!("difficulty {}\n", setup.difficulty));
+        text.push_str(&format!("generator {}\n", setup.generator));
+        text.push_str(&format!("num_tex {}\n", setup.num_tex));
+        text.push_str(&format!("key_index {}\n", setup.key_index));
+        text.push_str(&format!("start {:?}\n", setup.start));
+        let tuning = &setup.tuning;
+        text.push_str(&format!("speed {}\n", tuning.speed));
+        text.push_str(&format!("room_size {}\n", tuning.room_size));
+        text.push_str(&format!("scale {}\n", tuning.scale));
+        text.push_str(&format!("buffer {}\n", tuning.buffer));
+        text.push_str(&format!("door_width {}\n", tuning.door_width));
+        text.push_str(&format!("door_depth {}\n", tuning.door_depth));
+        text.push_str(&format!("ghosts {}\n", ghosts_text(&setup.ghosts)));
+        if let Some(levels) = &setup.levels {
+            text.push_str(&format!("level {}\n", setup.level));
+            text.push_str(&format!("levels {}\n", levels_text(levels)));
+        }
+        for (tick, retune) in self.retunes.iter() {
+            text.push_str(&format!("retune {} {}\n", tick, retune_text(retune)));
+        }
+        text.push_str(&format!(
+            "end {} {} {} {} {:08x} {:08x} {:016x}\n",
+            end.ticks, end.room, end.has_key, end.finished, end.x, end.z, end.hash
+        ));
+        text.push_str("---\n");
+        text.push_str(&Scripted::write(&self.frames));
+        return text;
+    }
+
+    pub fn parse(text: &str) -> Result<Recording, ReplayError> {
+        let (header, body) = text
+            .split_once("---\n")
+            .ok_or_else(|| ReplayError::Format("missing --- before the inputs".to_string()))?;
+        let mut fields = std::collections::HashMap::new();
+        let mut retunes = vec![];
+        for (_, line) in save::lines(header) {
+            match line.split_once(' ') {
+                Some(("retune", value)) => retunes.push(parse_retune(value)?),
+                Some((name, value)) => {
+                    fields.insert(name, value.trim());
+                }
+                None => {}
+            }
+        }
+        let field = |name: &str| {
+            fields
+                .get(name)
+                .copied()
+                .ok_or_else(|| ReplayError::Format(format!("missing {}", name)))
+        };
+        let number = |name: &str| {
+            field(name)?
+                .parse::<u64>()
+                .map_err(|_| ReplayError::Format(format!("bad {}", name)))
+        };
+        //f32 Display is the shortest text that parses back to the same bits
+        let float = |name: &str| {
+            field(name)?
+                .parse::<f32>()
+                .map_err(|_| ReplayError::Format(format!("bad {}", name)))
+        };
+        let start = match field("start")? {
+            "Mainscreen" => GameplayState::Mainscreen,
+            "Instructions" => GameplayState::Instructions,
+            "Play" => GameplayState::Play,
+            "TimeAttack" => GameplayState::TimeAttack,
+            "FinalScreen" => GameplayState::FinalScreen,
+            other => return Err(ReplayError::Format(format!("bad start {}", other))),
+        };
+        let setup = Setup {
+            seed: number("seed")?,
+            rooms: number("rooms")? as usize,
+            dead_ends: number("dead_ends")? as usize,
+            //recordings from before houses had storeys have no floors line
+            floors: if fields.contains_key("floors") {
+                number("floors")? as usize
+            } else {
+                1
+            },
+            difficulty: number("difficulty")? as usize,
+            generator: field("generator")?.parse().map_err(ReplayError::Format)?,
+            num_tex: number("num_tex")? as usize,
+            key_index: number("key_index")? as usize,
+            start,
+            tuning: Tuning {
+                speed: float("speed")?,
+                room_size: float("room_size")?,
+                scale: float("scale")?,
+                buffer: float("buffer")?,
+                door_width: float("door_width")?,
+                door_depth: float("door_depth")?,
+            },
+            ghosts: match fields.get("ghosts") {
+                Some(line) => parse_ghosts(&words(line))?,
+                //recordings from before ghosts were haunted by none
+                None => GhostRules::default(),
+            },
+            //and from before the endless game were classic games
+            levels: match fields.get("levels") {
+                Some(line) => Some(parse_levels(&words(line))?),
+                None => None,
+            },
+            level: if fields.contains_key("level") {
+                number("level")? as usize
+            } else {
+                1
+            },
+        };
+        if setup.level == 0 {
+            return Err(ReplayError::Format("bad level".to_string()));
+        }
+
+        let end: Vec<&str> = field("end")?.split_whitespace().collect();
+        let bad_end = || ReplayError::Format("bad end".to_string());
+        if end.len() != 7 {
+            return Err(bad_end());
+        }
+        let end = Snapshot {
+            ticks: end[0].parse().map_err(|_| bad_end())?,
+            room: end[1].parse().map_err(|_| bad_end())?,
+            has_key: end[2].parse().map_err(|_| bad_end())?,
+            finished: end[3].parse().map_err(|_| bad_end())?,
+            x: u32::from_str_radix(end[4], 16).map_err(|_| bad_end())?,
+            z: u32::from_str_radix(end[5], 16).map_err(|_| bad_end())?,
+            hash: u64::from_str_radix(end[6], 16).map_err(|_| bad_end())?,
+        };
+
+        let first_line = header.lines().count() + 2;
+        let frames = Scripted::parse_at(body, first_line)
+            .map_err(ReplayError::Script)?
+            .frames()
+            .to_vec();
+        if frames.len() != end.ticks {
+            return Err(ReplayError::Format(format!(
+                "end says {} ticks but there are {} inputs",
+                end.ticks,
+                frames.len()
+            )));
+        }
+        if retunes.windows(2).any(|pair| pair[0].0 > pair[1].0)
+            || retunes.last().is_some_and(|(tick, _)| *tick > frames.len())
+        {
+            return Err(ReplayError::Format("retunes out of order".to_string()));
+        }
+        return Ok(Recording {
+            setup,
+            frames,
+            retunes,
+            end,
+        });
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        return std::fs::write(path, self.to_text()).map_err(ReplayError::Io);
+    }
+
+    pub fn load(path: &Path) -> Result<Recording, ReplayError> {
+        let text = std::fs::read_to_string(path).map_err(ReplayError::Io)?;
+        return Recording::parse(&text);
+    }
+
+    //the reloads that came in just before the given tick's input
+    pub fn retunes_at(&self, tick: usize) -> impl Iterator<Item = &Retune> {
+        return self
+            .retunes
+            .iter()
+            .filter(move |(at, _)| *at == tick)
+            .map(|(_, retune)| retune);
+    }
+
+    //plays the inputs back from scratch and checks we land where the recording did
+    pub fn verify(&self) -> Result<Snapshot, ReplayError> {
+        let (mut state, mut rng) = self.setup.build();
+        for (tick, controls) in self.frames.iter().enumerate() {
+            for retune in self.retunes_at(tick) {
+                retune.apply(&mut state);
+            }
+            rules::update(&mut state, *controls, &mut rng);
+        }
+        let end = Snapshot::of(&state, self.frames.len());
+        if end != self.end {
+            return Err(ReplayError::Mismatch {
+                expected: self.end,
+                actual: end,
+            });
+        }
+        return Ok(end);
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Format(String),
+    Script(ScriptError),
+    Mismatch {
+        expected: Snapshot,
+        actual: Snapshot,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "{}", err),
+            ReplayError::Format(message) => write!(f, "not a replay: {}", message),
+            ReplayError::Script(err) => write!(f, "bad inputs: {}", err),
+            ReplayError::Mismatch { expected, actual } => write!(
+                f,
+                "replay diverged\n  recorded: {}\n  replayed: {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+fn words(line: &str) -> Vec<&str> {
+    return line.split_whitespace().collect();
+}
+
+//count, speed, speedup, max_speed, linger, reach and catch, in that order
+fn ghosts_text(ghosts: &GhostRules) -> String {
+    return format!(
+        "{} {} {} {} {} {} {}",
+        ghosts.count,
+        ghosts.speed,
+        ghosts.speedup,
+        ghosts.max_speed,
+        ghosts.linger,
+        ghosts.reach,
+        ghosts.catch
+    );
+}
+
+fn parse_ghosts(words: &[&str]) -> Result<GhostRules, ReplayError> {
+    let bad = || ReplayError::Format("bad ghosts".to_string());
+    if words.len() != 7 {
+        return Err(bad());
+    }
+    let float = |word: &str| word.parse::<f32>().map_err(|_| bad());
+    return Ok(GhostRules {
+        count: words[0].parse().map_err(|_| bad())?,
+        speed: float(words[1])?,
+        speedup: float(words[2])?,
+        max_speed: float(words[3])?,
+        linger: float(words[4])?,
+        reach: float(words[5])?,
+        catch: words[6].parse().map_err(|_| bad())?,
+    });
+}
+
+//six curves of start, step, min and max, in the order Levels lists them
+fn levels_text(levels: &Levels) -> String {
+    let mut curves = vec![];
+    for curve in [
+        levels.rooms,
+        levels.dead_ends,
+        levels.loops,
+        levels.locked,
+        levels.ghosts,
+        levels.time_limit,
+    ] {
+        curves.push(format!(
+            "{} {} {} {}",
+            curve.start, curve.step, curve.min, curve.max
+        ));
+    }
+    return curves.join(" ");
+}
+
+fn parse_levels(words: &[&str]) -> Result<Levels, ReplayError> {
+    let bad = || ReplayError::Format("bad levels".to_string());
+    if words.len() != 24 {
+        return Err(bad());
+    }
+    let mut values = vec![];
+    for word in words {
+        values.push(word.parse::<f32>().map_err(|_| bad())?);
+    }
+    let curve = |i: usize| Curve {
+        start: values[i * 4],
+        step: values[i * 4 + 1],
+        min: values[i * 4 + 2],
+        max: values[i * 4 + 3],
+    };
+    return Ok(Levels {
+        rooms: curve(0),
+        dead_ends: curve(1),
+        loops: curve(2),
+        locked: curve(3),
+        ghosts: curve(4),
+        time_limit: curve(5),
+    });
+}
+
+//floors, difficulty, generator, the six tuning numbers in the order the header
+//has them, then the ghosts and, in the endless game, the levels
+fn retune_text(retune: &Retune) -> String {
+    let tuning = &retune.tuning;
+    let mut text = format!(
+        "{} {} {} {} {} {} {} {} {} {}",
+        retune.floors,
+        retune.difficulty,
+        retune.generator,
+        tuning.speed,
+        tuning.room_size,
+        tuning.scale,
+        tuning.buffer,
+        tuning.door_width,
+        tuning.door_depth,
+        ghosts_text(&retune.ghosts)
+    );
+    if let Some(levels) = &retune.levels {
+        text.push(' ');
+        text.push_str(&levels_text(levels));
+    }
+    return text;
+}
+
+//the tick comes first
+fn parse_retune(line: &str) -> Result<(usize, Retune), ReplayError> {
+    let bad = || ReplayError::Format("bad retune".to_string());
+    let words = words(line);
+    if words.len() != 17 && words.len() != 17 + 24 {
+        return Err(bad());
+    }
+    let number = |word: &str| word.parse::<usize>().map_err(|_| bad());
+    let float = |word: &str| word.parse::<f32>().map_err(|_| bad());
+    let retune = Retune {
+        floors: number(words[1])?,
+        difficulty: number(words[2])?,
+        generator: words[3].parse().map_err(|_| bad())?,
+        tuning: Tuning {
+            speed: float(words[4])?,
+            room_size: float(words[5])?,
+            scale: float(words[6])?,
+            buffer: float(words[7])?,
+            door_width: float(words[8])?,
+            door_depth: float(words[9])?,
+        },
+        ghosts: parse_ghosts(&words[10..17])?,
+        levels: if words.len() > 17 {
+            Some(parse_levels(&words[17..])?)
+        } else {
+            None
+        },
+    };
+    return Ok((number(words[0])?, retune));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Explorer;
+    use crate::sim::Driver;
+
+    fn setup() -> Setup {
+        return Setup {
+            seed: 77,
+            rooms: 5,
+            dead_ends: 2,
+            floors: 1,
+            difficulty: 3,
+            generator: Generator::Path,
+            num_tex: 8,
+            key_index: 3,
+            start: GameplayState::Mainscreen,
+            tuning: Tuning::default(),
+            ghosts: GhostRules::default(),
+            levels: None,
+            level: 1,
+        };
+    }
+
+    //lets the bot play a house and a half while recording it
+    fn record() -> Recording {
+        let (mut state, mut rng) = setup().build();
+        let mut recorder = Recorder::new(setup());
+        let mut bot = Explorer::new();
+        let mut wins = 0;
+        for _ in 0..50_000 {
+            let mut controls = bot.controls(&state);
+            if state.gameplaystate == GameplayState::FinalScreen {
+                controls.restart = true;
+                bot = Explorer::new();
+                wins += 1;
+            }
+            if wins == 1 && state.has_key {
+                break;
+            }
+            recorder.push(controls);
+            rules::update(&mut state, controls, &mut rng);
+        }
+        return recorder.finish(&state);
+    }
+
+    #[test]
+    fn recordings_replay_exactly() {
+        let recording = record();
+        assert!(recording.end.has_key);
+        assert_eq!(recording.verify().unwrap(), recording.end);
+    }
+
+    #[test]
+    fn recordings_survive_the_round_trip_to_text() {
+        let recording = record();
+        let parsed = Recording::parse(&recording.to_text()).unwrap();
+        assert_eq!(parsed, recording);
+    }
+
+    #[test]
+    fn tampered_inputs_are_caught() {
+        let mut recording = record();
+        //a door resets your position, so nudge the very last step
+        let last = recording.frames.last_mut().unwrap();
+        *last = if last.right {
+            Controls::from_keys("A").unwrap()
+        } else {
+            Controls::from_keys("D").unwrap()
+        };
+        match recording.verify() {
+            Err(ReplayError::Mismatch { expected, actual }) => {
+                assert_eq!(expected.ticks, actual.ticks);
+                assert_ne!(expected, actual);
+            }
+            other => panic!("expected a mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn odd_tuning_survives_the_round_trip_to_text() {
+        let mut recording = record();
+        recording.setup.tuning.speed = 0.1 + 0.2;
+        let parsed = Recording::parse(&recording.to_text()).unwrap();
+        assert_eq!(
+            parsed.setup.tuning.speed.to_bits(),
+            (0.1f32 + 0.2).to_bits()
+        );
+    }
+
+    #[test]
+    fn recordings_from_before_storeys_are_one_storey_houses() {
+        let recording = record();
+        let text = recording.to_text().replace("floors 1\n", "");
+        assert_eq!(Recording::parse(&text).unwrap(), recording);
+        let mut stacked = recording.setup;
+        stacked.floors = 3;
+        let (state, _) = stacked.build();
+        assert!(state.rooms[stacked.key_index].storey > 0);
+        assert_eq!(state.floors, 3);
+    }
+
+    #[test]
+    fn haunted_houses_replay_exactly() {
+        let mut recording = record();
+        recording.setup.ghosts.count = 2;
+        recording.setup.ghosts.catch = crate::ghost::Catch::End;
+        let (mut state, mut rng) = recording.setup.build();
+        assert_eq!(state.ghosts.len(), 2);
+        for controls in recording.frames.iter() {
+            rules::update(&mut state, *controls, &mut rng);
+        }
+        recording.end = Snapshot::of(&state, recording.frames.len());
+        let parsed = Recording::parse(&recording.to_text()).unwrap();
+        assert_eq!(parsed, recording);
+        assert_eq!(parsed.verify().unwrap(), recording.end);
+        let text = recording.to_text().replace(" end\n", " eat\n");
+        assert!(Recording::parse(&text).is_err());
+    }
+
+    #[test]
+    fn time_attacks_replay_to_the_tick() {
+        let mut recording = record();
+        recording.setup.start = GameplayState::TimeAttack;
+        let (mut state, mut rng) = recording.setup.build();
+        for controls in recording.frames.iter() {
+            rules::update(&mut state, *controls, &mut rng);
+        }
+        assert!(state.clock.is_some_and(|ticks| ticks > 0));
+        recording.end = Snapshot::of(&state, recording.frames.len());
+        let parsed = Recording::parse(&recording.to_text()).unwrap();
+        assert_eq!(parsed, recording);
+        assert_eq!(parsed.verify().unwrap(), recording.end);
+    }
+
+    #[test]
+    fn endless_levels_replay_exactly() {
+        let mut recording = record();
+        let levels = Levels {
+            time_limit: Curve {
+                start: 100.0,
+                step: -0.5,
+                min: 10.0,
+                max: 100.0,
+            },
+            ..Levels::default()
+        };
+        let stage = levels.stage(9);
+        let setup = &mut recording.setup;
+        (setup.levels, setup.level) = (Some(levels), 9);
+        (setup.rooms, setup.dead_ends) = (stage.rooms, stage.dead_ends);
+        setup.key_index = stage.rooms - 1;
+        let (mut state, mut rng) = recording.setup.build();
+        assert!(state.doors.iter().any(|door| door.locked));
+        assert_eq!(state.ghosts.len(), stage.ghosts);
+        for controls in recording.frames.iter() {
+            rules::update(&mut state, *controls, &mut rng);
+        }
+        recording.end = Snapshot::of(&state, recording.frames.len());
+        let parsed = Recording::parse(&recording.to_text()).unwrap();
+        assert_eq!(parsed, recording);
+        assert_eq!(parsed.verify().unwrap(), recording.end);
+        let text = recording.to_text().replace("level 9\n", "level 0\n");
+        assert!(Recording::parse(&text).is_err());
+    }
+
+    #[test]
+    fn recordings_made_across_a_reload_replay_exactly() {
+        let (mut state, mut rng) = setup().build();
+        let mut recorder = Recorder::new(setup());
+        let mut bot = Explorer::new();
+        let mut wins = 0;
+        for tick in 0..50_000 {
+            //halfway through the first house the walk gets quicker and the next one bigger
+            if tick == 200 {
+                let retune = Retune {
+                    tuning: Tuning {
+                        speed: state.tuning.speed * 1.5,
+                        ..state.tuning
+                    },
+                    floors: 2,
+                    difficulty: 5,
+                    generator: Generator::Tree,
+                    ghosts: GhostRules::default(),
+                    levels: None,
+                };
+                recorder.retune(retune);
+                retune.apply(&mut state);
+            }
+            let mut controls = bot.controls(&state);
+            if state.gameplaystate == GameplayState::FinalScreen {
+                controls.restart = true;
+                bot = Explorer::new();
+                wins += 1;
+            }
+            if wins == 1 && state.has_key {
+                break;
+            }
+            recorder.push(controls);
+            rules::update(&mut state, controls, &mut rng);
+        }
+        assert_eq!(state.floors, 2);
+        let recording = recorder.finish(&state);
+        assert_eq!(recording.retunes.len(), 1);
+        assert_eq!(recording.verify().unwrap(), recording.end);
+        let parsed = Recording::parse(&recording.to_text()).unwrap();
+        assert_eq!(parsed, recording);
+        //without the reload the replay walks slower into a different house
+        let mut unaware = recording.clone();
+        unaware.retunes.clear();
+        assert!(unaware.verify().is_err());
+    }
+
+    #[test]
+    fn a_different_seed_is_a_different_run() {
+        let mut recording = record();
+        recording.setup.seed += 1;
+        assert!(recording.verify().is_err());
+    }
+
+    #[test]
+    fn broken_files_say_what_is_wrong() {
+        let text = record().to_text();
+        let err = Recording::parse(&text.replace("seed", "sede")).unwrap_err();
+        assert_eq!(err.to_string(), "not a replay: missing seed");
+        let err = Recording::parse(&text.replace("---\n", "")).unwrap_err();
+        assert!(err.to_string().contains("missing ---"));
+        let err = Recording::parse(&format!("{}1 Q\n", text)).unwrap_err();
+        assert!(err.to_string().starts_with("bad inputs: line"));
+    }
+}
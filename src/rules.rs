@@ -0,0 +1,591 @@
+use crate::collision::*;
+use crate::config::Tuning;
+use crate::geometry::*;
+use crate::ghost::{self, Catch, Ghost};
+use crate::state::*;
+use crate::types::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use ultraviolet::Vec3;
+
+//where the chest (and the block under it) sits in the first room
+pub const CHEST_POS: Vec3 = Vec3::new(0.0, 5.0, 0.0);
+pub const CHEST_EDGE: (f32, f32) = (7.75, 7.75);
+//where the key floats in the key room
+pub const KEY_POS: Vec3 = Vec3::new(0.0, 10.0, 0.0);
+pub const KEY_EDGE: (f32, f32) = (7.75, 3.0);
+
+// One tick of player input, already decoupled from the windowing layer. The
+// front end fills this from the keyboard; bots and replays build it directly.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct Controls {
+    pub up: bool,          //W
+    pub down: bool,        //S, which also starts the game from the main screen
+    pub left: bool,        //A
+    pub right: bool,       //D
+    pub play: bool,        //P on the instructions screen
+    pub time_attack: bool, //T on the instructions screen
+    pub restart: bool,     //R on the final screen
+}
+
+impl Controls {
+    pub fn is_moving(&self) -> bool {
+        return self.up || self.down || self.left || self.right;
+    }
+
+    //keys as typed: "WD" walks up and right, "-" is no input
+    pub fn from_keys(keys: &str) -> Option<Controls> {
+        let mut controls = Controls::default();
+        if keys == "-" {
+            return Some(controls);
+        }
+        for key in keys.chars() {
+            match key.to_ascii_uppercase() {
+                'W' => controls.up = true,
+                'A' => controls.left = true,
+                'S' => controls.down = true,
+                'D' => controls.right = true,
+                'P' => controls.play = true,
+                'T' => controls.time_attack = true,
+                'R' => controls.restart = true,
+                _ => return None,
+            }
+        }
+        return Some(controls);
+    }
+
+    pub fn to_keys(&self) -> String {
+        let mut keys = String::new();
+        for (down, key) in [
+            (self.up, 'W'),
+            (self.left, 'A'),
+            (self.down, 'S'),
+            (self.right, 'D'),
+            (self.play, 'P'),
+            (self.time_attack, 'T'),
+            (self.restart, 'R'),
+        ] {
+            if down {
+                keys.push(key);
+            }
+        }
+        if keys.is_empty() {
+            keys.push('-');
+        }
+        return keys;
+    }
+}
+
+// Things that happened during a tick, so the front end can play animations and
+// sounds and the headless tools can keep score without diffing states.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    ScreenChanged(GameplayState),
+    Moved,
+    Blocked,
+    DoorTraversed { door: usize, from: usize, to: usize },
+    KeyCollected,
+    ChestOpened,
+    Caught,
+    OutOfTime,
+    Restarted,
+}
+
+pub fn update<R: Rng>(state: &mut GameState, controls: Controls, rng: &mut R) -> Vec<Event> {
+    let mut events = vec![];
+
+    //Press S to play
+    if state.gameplaystate == GameplayState::Mainscreen && controls.down {
+        state.gameplaystate = GameplayState::Instructions;
+        events.push(Event::ScreenChanged(state.gameplaystate));
+    }
+
+    //Press P to play, or T to play against the clock
+    if state.gameplaystate == GameplayState::Instructions {
+        if controls.play {
+            state.gameplaystate = GameplayState::Play;
+            events.push(Event::ScreenChanged(state.gameplaystate));
+        } else if controls.time_attack {
+            state.gameplaystate = GameplayState::TimeAttack;
+            state.clock = Some(0);
+            events.push(Event::ScreenChanged(state.gameplaystate));
+        }
+    }
+    //controls for gameplaystate play
+    else if state.gameplaystate.is_playing() {
+        //the clock waits for the first step, then runs until the chest opens
+        if let Some(clock) = &mut state.clock {
+            if *clock > 0 || controls.is_moving() {
+                *clock += 1;
+            }
+        }
+        if let Some(left) = &mut state.time_left {
+            *left = left.saturating_sub(1);
+        }
+        play(state, controls, &mut events);
+        if state.gameplaystate.is_playing() && ghost::step(state, rng) {
+            caught(state, &mut events);
+        }
+        //opening the chest on the last tick still counts
+        if state.gameplaystate.is_playing() && state.time_left == Some(0) {
+            state.out_of_time = true;
+            state.gameplaystate = GameplayState::FinalScreen;
+            events.push(Event::OutOfTime);
+            events.push(Event::ScreenChanged(state.gameplaystate));
+        }
+    }
+    //restart the game by pressing R, and randomize
+    else if state.gameplaystate == GameplayState::FinalScreen && controls.restart {
+        *state = state.restart(rng);
+        events.push(Event::Restarted);
+    }
+    return events;
+}
+
+fn play(state: &mut GameState, controls: Controls, events: &mut Vec<Event>) {
+    let tuning = state.tuning;
+    let (push, speed) = (tuning.buffer / 2.0, tuning.speed);
+    let shape = state.rooms[state.current_room].shape;
+    let player = &mut state.player;
+    if state.current_room == 0
+        && check_item_collisions(player.pos, CHEST_POS, CHEST_EDGE.0, CHEST_EDGE.1)
+    {
+        // check if collide with item and spawn back
+        player.pos += match player.dir {
+            Direction::North => Vec3::new(0.0, 0.0, push),
+            Direction::South => Vec3::new(0.0, 0.0, -push),
+            Direction::East => Vec3::new(-push, 0.0, 0.0),
+            Direction::West => Vec3::new(push, 0.0, 0.0),
+        };
+        events.push(Event::Blocked);
+    } else {
+        if controls.is_moving() {
+            events.push(Event::Moved);
+        }
+        if controls.up {
+            walk(
+                player,
+                Direction::North,
+                Vec3::new(0.0, 0.0, -speed),
+                shape,
+                &tuning,
+            );
+        }
+        if controls.down {
+            walk(
+                player,
+                Direction::South,
+                Vec3::new(0.0, 0.0, speed),
+                shape,
+                &tuning,
+            );
+        }
+        if controls.left {
+            walk(
+                player,
+                Direction::West,
+                Vec3::new(-speed, 0.0, 0.0),
+                shape,
+                &tuning,
+            );
+        }
+        if controls.right {
+            walk(
+                player,
+                Direction::East,
+                Vec3::new(speed, 0.0, 0.0),
+                shape,
+                &tuning,
+            );
+        }
+    }
+
+    //locked doors stay shut until the key is found
+    let from = state.current_room;
+    let through = state.room_doors(from).find(|(_, door)| {
+        (!door.locked || state.has_key) && check_collisions(state.player.pos, *door, shape, &tuning)
+    });
+    if let Some((dooridx, door)) = through {
+        state.current_room = door.target;
+        state.visited[door.target] = true;
+        state.doors[dooridx].uses += 1;
+        let back = state.door_back(from, door);
+        state.player.pos = get_spawn_pos(state.rooms[door.target].shape, back, &tuning);
+        events.push(Event::DoorTraversed {
+            door: dooridx,
+            from,
+            to: door.target,
+        });
+    }
+
+    //checking collision with key
+    if state.current_room == state.key_index
+        && !state.has_key
+        && check_item_collisions(state.player.pos, KEY_POS, KEY_EDGE.0, KEY_EDGE.1)
+    {
+        state.has_key = true;
+        events.push(Event::KeyCollected);
+    }
+
+    //if we have the key, are in first room, and are collided with the chest
+    if state.current_room == 0
+        && state.has_key
+        && check_item_collisions(state.player.pos, CHEST_POS, CHEST_EDGE.0, CHEST_EDGE.1)
+    {
+        state.is_finished = true;
+        state.gameplaystate = GameplayState::FinalScreen;
+        events.push(Event::ChestOpened);
+        events.push(Event::ScreenChanged(state.gameplaystate));
+    }
+}
+
+// A ghost reached the player. Every ghost in the room goes home, and either
+// the run ends or the player goes back to the first room, which ghosts never
+// come into.
+fn caught(state: &mut GameState, events: &mut Vec<Event>) {
+    let (room, rules) = (state.current_room, state.ghost_rules);
+    for ghost in state.ghosts.iter_mut().filter(|ghost| ghost.room == room) {
+        *ghost = Ghost::at_home(ghost.home, &rules);
+    }
+    events.push(Event::Caught);
+    match rules.catch {
+        Catch::End => {
+            state.caught = true;
+            state.gameplaystate = GameplayState::FinalScreen;
+            events.push(Event::ScreenChanged(state.gameplaystate));
+        }
+        Catch::Start => {
+            state.current_room = 0;
+            state.player.pos = START_POS;
+        }
+    }
+}
+
+//turn to face the way we're walking, then step if that keeps us in the room
+fn walk(player: &mut Player, dir: Direction, step: Vec3, shape: Shape, tuning: &Tuning) {
+    player.dir = dir;
+    if in_room_bounds(player.pos + step, shape, tuning.room_size) {
+        player.pos += step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Curve, GhostRules, Levels};
+    use crate::map::Generator;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn menus_advance_in_order() {
+        let mut state = two_rooms();
+        let mut rng = StdRng::seed_from_u64(0);
+        state.gameplaystate = GameplayState::Mainscreen;
+        update(&mut state, Controls::default(), &mut rng);
+        assert_eq!(state.gameplaystate, GameplayState::Mainscreen);
+        let play = Controls {
+            play: true,
+            ..Controls::default()
+        };
+        update(&mut state, play, &mut rng);
+        assert_eq!(state.gameplaystate, GameplayState::Mainscreen);
+        let start = Controls {
+            down: true,
+            ..Controls::default()
+        };
+        update(&mut state, start, &mut rng);
+        assert_eq!(state.gameplaystate, GameplayState::Instructions);
+        update(&mut state, play, &mut rng);
+        assert_eq!(state.gameplaystate, GameplayState::Play);
+    }
+
+    #[test]
+    fn walls_stop_the_player() {
+        let mut state = two_rooms();
+        let mut rng = StdRng::seed_from_u64(0);
+        let right = Controls {
+            right: true,
+            ..Controls::default()
+        };
+        for _ in 0..100 {
+            update(&mut state, right, &mut rng);
+        }
+        assert!(state.player.pos.x < state.tuning.room_size / 2.0);
+        assert_eq!(state.player.dir, Direction::East);
+        assert_eq!(state.current_room, 0);
+    }
+
+    #[test]
+    fn walking_into_a_door_changes_room() {
+        let mut state = two_rooms();
+        let mut rng = StdRng::seed_from_u64(0);
+        state.player.pos = Vec3::new(0.0, 0.0, 20.0);
+        let down = Controls {
+            down: true,
+            ..Controls::default()
+        };
+        let mut events = vec![];
+        for _ in 0..40 {
+            events.extend(update(&mut state, down, &mut rng));
+            if state.current_room == 1 {
+                break;
+            }
+        }
+        assert_eq!(state.current_room, 1);
+        assert!(events.contains(&Event::DoorTraversed {
+            door: 0,
+            from: 0,
+            to: 1
+        }));
+        assert_eq!(
+            state.player.pos,
+            get_spawn_pos(Shape::SQUARE, state.doors[1], &state.tuning)
+        );
+        assert_eq!(state.visited, [true, true]);
+        assert_eq!(state.doors[0].uses, 1);
+        assert_eq!(state.doors[1].uses, 0);
+    }
+
+    #[test]
+    fn locked_doors_open_once_the_key_is_found() {
+        let mut state = two_rooms();
+        let mut rng = StdRng::seed_from_u64(0);
+        state.doors[0].locked = true;
+        state.player.pos = Vec3::new(0.0, 0.0, 20.0);
+        let down = Controls {
+            down: true,
+            ..Controls::default()
+        };
+        for _ in 0..40 {
+            update(&mut state, down, &mut rng);
+        }
+        assert_eq!(state.current_room, 0);
+        state.has_key = true;
+        update(&mut state, down, &mut rng);
+        assert_eq!(state.current_room, 1);
+    }
+
+    #[test]
+    fn doors_lead_out_of_the_door_back() {
+        let mut state = two_rooms();
+        let mut rng = StdRng::seed_from_u64(0);
+        state.rooms[1].shape = Shape {
+            width: STEPS / 2,
+            depth: STEPS,
+            notch: None,
+        };
+        state.doors[0].offset = 2;
+        state.doors[1].offset = -1;
+        state.player.pos = Vec3::new(15.0, 0.0, 20.0);
+        let down = Controls {
+            down: true,
+            ..Controls::default()
+        };
+        for _ in 0..40 {
+            update(&mut state, down, &mut rng);
+        }
+        assert_eq!(state.current_room, 1);
+        //a step left of the middle of the corridor's south wall, a buffer in
+        let step = state.tuning.room_size / STEPS as f32;
+        assert_eq!(state.player.pos.x, -step);
+        assert!(state.player.pos.z > -state.tuning.room_size / 2.0);
+        //and its walls are a quarter of a room in from the middle
+        let right = Controls {
+            right: true,
+            ..Controls::default()
+        };
+        for _ in 0..100 {
+            update(&mut state, right, &mut rng);
+        }
+        assert!(state.player.pos.x < 2.0 * step);
+        assert!(state.player.pos.x > 2.0 * step - state.tuning.speed - 1e-3);
+    }
+
+    #[test]
+    fn key_then_chest_finishes_the_house() {
+        let mut state = two_rooms();
+        let mut rng = StdRng::seed_from_u64(0);
+        state.current_room = 1;
+        state.player.pos = Vec3::new(0.0, 0.0, 0.0);
+        let events = update(&mut state, Controls::default(), &mut rng);
+        assert!(state.has_key);
+        assert_eq!(events, vec![Event::KeyCollected]);
+
+        //the chest is checked after moving, so step into it
+        state.current_room = 0;
+        state.player.pos = Vec3::new(0.0, 0.0, 8.0);
+        let up = Controls {
+            up: true,
+            ..Controls::default()
+        };
+        let events = update(&mut state, up, &mut rng);
+        assert!(state.is_finished);
+        assert_eq!(state.gameplaystate, GameplayState::FinalScreen);
+        assert!(events.contains(&Event::ChestOpened));
+    }
+
+    #[test]
+    fn the_chest_pushes_back_without_the_key() {
+        let mut state = two_rooms();
+        let mut rng = StdRng::seed_from_u64(0);
+        state.player = Player {
+            pos: Vec3::new(0.0, 0.0, 7.0),
+            dir: Direction::North,
+        };
+        let events = update(&mut state, Controls::default(), &mut rng);
+        assert_eq!(events, vec![Event::Blocked]);
+        assert_eq!(state.player.pos.z, 7.0 + state.tuning.buffer / 2.0);
+        assert!(!state.is_finished);
+    }
+
+    #[test]
+    fn keys_round_trip() {
+        for keys in ["-", "W", "AS", "WASD", "P", "T", "R"] {
+            assert_eq!(Controls::from_keys(keys).unwrap().to_keys(), keys);
+        }
+        assert_eq!(Controls::from_keys("wd"), Controls::from_keys("WD"));
+        assert_eq!(Controls::from_keys("WX"), None);
+    }
+
+    #[test]
+    fn restart_grows_the_house() {
+        let mut state = two_rooms();
+        let mut rng = StdRng::seed_from_u64(3);
+        state.gameplaystate = GameplayState::FinalScreen;
+        let restart = Controls {
+            restart: true,
+            ..Controls::default()
+        };
+        let events = update(&mut state, restart, &mut rng);
+        assert_eq!(events, vec![Event::Restarted]);
+        assert_eq!(state.gameplaystate, GameplayState::Play);
+        assert_eq!(state.max_rooms, 2 + state.difficulty);
+        assert!(state.key_index >= 1 && state.key_index < state.max_rooms);
+        assert!(!state.has_key);
+    }
+
+    #[test]
+    fn the_time_attack_clock_runs_from_the_first_step_to_the_chest() {
+        let mut state = two_rooms();
+        let mut rng = StdRng::seed_from_u64(0);
+        state.gameplaystate = GameplayState::Instructions;
+        let attack = Controls {
+            time_attack: true,
+            ..Controls::default()
+        };
+        update(&mut state, attack, &mut rng);
+        assert_eq!(state.gameplaystate, GameplayState::TimeAttack);
+        for _ in 0..30 {
+            update(&mut state, Controls::default(), &mut rng);
+        }
+        assert_eq!(state.clock, Some(0));
+        let up = Controls {
+            up: true,
+            ..Controls::default()
+        };
+        update(&mut state, up, &mut rng);
+        for _ in 0..9 {
+            update(&mut state, Controls::default(), &mut rng);
+        }
+        assert_eq!(state.clock, Some(10));
+
+        //opening the chest stops it
+        state.has_key = true;
+        state.player.pos = Vec3::new(0.0, 0.0, 8.0);
+        update(&mut state, up, &mut rng);
+        assert_eq!(state.gameplaystate, GameplayState::FinalScreen);
+        update(&mut state, Controls::default(), &mut rng);
+        assert_eq!(state.clock, Some(11));
+
+        //and the next house is against a fresh one
+        let next = state.restart(&mut rng);
+        assert_eq!(next.gameplaystate, GameplayState::TimeAttack);
+        assert_eq!(next.clock, Some(0));
+    }
+
+    #[test]
+    fn running_out_of_time_loses_the_house() {
+        let mut state = two_rooms();
+        let mut rng = StdRng::seed_from_u64(0);
+        state.time_left = Some(3);
+        for _ in 0..2 {
+            update(&mut state, Controls::default(), &mut rng);
+        }
+        assert_eq!(state.gameplaystate, GameplayState::Play);
+        let events = update(&mut state, Controls::default(), &mut rng);
+        assert_eq!(
+            events,
+            vec![
+                Event::OutOfTime,
+                Event::ScreenChanged(GameplayState::FinalScreen)
+            ]
+        );
+        assert!(state.out_of_time && !state.is_finished);
+        let next = state.restart(&mut rng);
+        assert_eq!((next.max_rooms, next.cleared), (2, 0));
+        assert_eq!(next.player.pos, START_POS);
+    }
+
+    #[test]
+    fn the_endless_game_goes_up_a_level_for_every_chest() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let levels = Levels {
+            time_limit: Curve {
+                start: 60.0,
+                step: 0.0,
+                min: 0.0,
+                max: 60.0,
+            },
+            ..Levels::default()
+        };
+        let rules = GhostRules::default();
+        let state = GameState::for_level(levels, 3, Generator::Path, 1, 8, rules, &mut rng);
+        let stage = levels.stage(3);
+        assert_eq!((state.level, state.cleared), (3, 2));
+        assert_eq!(state.max_rooms, stage.rooms);
+        assert_eq!(state.rooms.len(), stage.rooms + stage.dead_ends);
+        assert_eq!(state.ghosts.len(), stage.ghosts);
+        assert_eq!(state.time_left, Some(60 * 60));
+
+        let mut won = state.clone();
+        won.is_finished = true;
+        let next = won.restart(&mut rng);
+        assert_eq!((next.level, next.max_rooms), (4, levels.stage(4).rooms));
+        let mut lost = state.clone();
+        lost.out_of_time = true;
+        let again = lost.restart(&mut rng);
+        assert_eq!((again.level, again.max_rooms), (3, stage.rooms));
+        assert_eq!(again.time_left, Some(60 * 60));
+    }
+
+    #[test]
+    fn a_ghost_sends_you_back_or_ends_the_run() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for catch in [Catch::Start, Catch::End] {
+            let mut state = two_rooms();
+            state.rooms.push(Room::new(vec![], 0));
+            state.ghost_rules.catch = catch;
+            state.ghosts = vec![Ghost::at_home(2, &state.ghost_rules)];
+            state.ghosts[0].room = 1;
+            state.current_room = 1;
+            state.player.pos = Vec3::new(1.0, 0.0, 0.0);
+            let events = update(&mut state, Controls::default(), &mut rng);
+            assert!(events.contains(&Event::Caught));
+            assert_eq!(state.ghosts[0].room, 2);
+            match catch {
+                Catch::Start => {
+                    assert_eq!((state.current_room, state.player.pos), (0, START_POS));
+                    assert_eq!(state.gameplaystate, GameplayState::Play);
+                }
+                Catch::End => {
+                    assert!(state.caught && !state.is_finished);
+                    assert_eq!(state.gameplaystate, GameplayState::FinalScreen);
+                    //the next house is no bigger, and doesn't count as cleared
+                    let next = state.restart(&mut rng);
+                    assert_eq!((next.max_rooms, next.cleared), (2, 0));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+use std::path::Path;
+
+// The small files kept between sessions: best times, the daily history and
+// endless progress. Each starts with a `#` line saying what it is and holds
+// one record per line, its words split by spaces; `#` starts a comment
+// anywhere. A type only says how to write its records and read one back.
+pub trait SaveFile: Default {
+    const HEADER: &'static str; //the first line, without its #
+    const KIND: &'static str; //what a broken file is said not to be
+
+    fn records(&self) -> Vec<String>;
+
+    //None when the words aren't one of this file's records
+    fn read(&mut self, words: &[&str]) -> Option<()>;
+
+    fn to_text(&self) -> String {
+        let mut text = format!("# {}\n", Self::HEADER);
+        for record in self.records() {
+            text.push_str(&record);
+            text.push('\n');
+        }
+        return text;
+    }
+
+    fn parse(text: &str) -> Result<Self, Error> {
+        let mut file = Self::default();
+        for (n, line) in lines(text) {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if file.read(&words).is_none() {
+                return Err(Error::Format {
+                    kind: Self::KIND,
+                    line: n,
+                    text: line.to_string(),
+                });
+            }
+        }
+        return Ok(file);
+    }
+
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        return std::fs::write(path, self.to_text()).map_err(Error::Io);
+    }
+
+    //no file yet means nothing saved yet
+    fn load_or_default(path: &Path) -> Result<Self, Error> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path).map_err(Error::Io)?;
+        return Self::parse(&text);
+    }
+}
+
+//the lines that say something, numbered from 1, with comments cut off
+pub fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    return text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split('#').next().unwrap().trim()))
+        .filter(|(_, line)| !line.is_empty());
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Format {
+        kind: &'static str,
+        line: usize,
+        text: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Format { kind, line, text } => {
+                write!(f, "not {}: line {}: {}", kind, line, text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a name and a count per line
+    #[derive(Debug, Default, PartialEq)]
+    struct Tally {
+        counts: Vec<(String, u32)>,
+    }
+
+    impl SaveFile for Tally {
+        const HEADER: &'static str = "tally: name, count";
+        const KIND: &'static str = "a tally";
+
+        fn records(&self) -> Vec<String> {
+            return self
+                .counts
+                .iter()
+                .map(|(name, count)| format!("{} {}", name, count))
+                .collect();
+        }
+
+        fn read(&mut self, words: &[&str]) -> Option<()> {
+            if let [name, count] = words {
+                self.counts.push((name.to_string(), count.parse().ok()?));
+                return Some(());
+            }
+            return None;
+        }
+    }
+
+    #[test]
+    fn files_survive_the_round_trip_to_text() {
+        let tally = Tally {
+            counts: vec![("ghosts".to_string(), 3), ("keys".to_string(), 1)],
+        };
+        let text = tally.to_text();
+        assert_eq!(text, "# tally: name, count\nghosts 3\nkeys 1\n");
+        assert_eq!(Tally::parse(&text).unwrap(), tally);
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let text = "# tally\n\n  ghosts 3 # so far\n# keys 1\n";
+        assert_eq!(Tally::parse(text).unwrap().counts.len(), 1);
+    }
+
+    #[test]
+    fn broken_lines_say_where_they_are() {
+        let err = Tally::parse("# tally\nghosts 3\nkeys one\n").unwrap_err();
+        assert_eq!(err.to_string(), "not a tally: line 3: keys one");
+    }
+
+    #[test]
+    fn a_missing_file_is_an_empty_one() {
+        let path = Path::new("no/such/tally.txt");
+        assert_eq!(Tally::load_or_default(path).unwrap(), Tally::default());
+    }
+}
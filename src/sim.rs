@@ -0,0 +1,255 @@
+use crate::rules::{self, Controls, Event};
+use crate::save;
+use crate::state::*;
+use crate::DT;
+use rand::Rng;
+use std::fmt;
+
+// Anything that can play the game: a script, a bot or a replay. It sees the
+// same state the renderer would and answers with one tick of input.
+pub trait Driver {
+    fn controls(&mut self, state: &GameState) -> Controls;
+}
+
+impl<D: Driver + ?Sized> Driver for Box<D> {
+    fn controls(&mut self, state: &GameState) -> Controls {
+        return (**self).controls(state);
+    }
+}
+
+//plays back a fixed list of inputs, then lets go of every key
+#[derive(Clone)]
+pub struct Scripted {
+    script: Vec<Controls>,
+    tick: usize,
+}
+
+impl Scripted {
+    pub fn new(script: Vec<Controls>) -> Self {
+        return Scripted { script, tick: 0 };
+    }
+
+    //one "<ticks> <keys>" pair per line, e.g. "30 WD"; # starts a comment
+    pub fn parse(text: &str) -> Result<Scripted, ScriptError> {
+        return Scripted::parse_at(text, 1);
+    }
+
+    //same as parse, for scripts that start partway into a file
+    pub(crate) fn parse_at(text: &str, first_line: usize) -> Result<Scripted, ScriptError> {
+        let mut script = vec![];
+        for (n, line) in save::lines(text) {
+            let error = |message: &str| ScriptError {
+                line: n + first_line - 1,
+                message: message.to_string(),
+            };
+            let mut words = line.split_whitespace();
+            let ticks: usize = words
+                .next()
+                .unwrap()
+                .parse()
+                .map_err(|_| error("expected a tick count"))?;
+            let keys = words.next().ok_or_else(|| error("expected keys"))?;
+            if words.next().is_some() {
+                return Err(error("expected only a tick count and keys"));
+            }
+            let controls = Controls::from_keys(keys).ok_or_else(|| error("unknown key"))?;
+            script.extend(vec![controls; ticks]);
+        }
+        return Ok(Scripted::new(script));
+    }
+
+    //run-length encodes inputs in the format parse reads
+    pub fn write(frames: &[Controls]) -> String {
+        let mut text = String::new();
+        let mut n = 0;
+        while n < frames.len() {
+            let run = frames[n..]
+                .iter()
+                .take_while(|controls| **controls == frames[n])
+                .count();
+            text.push_str(&format!("{} {}\n", run, frames[n].to_keys()));
+            n += run;
+        }
+        return text;
+    }
+
+    pub fn frames(&self) -> &[Controls] {
+        return &self.script;
+    }
+
+    pub fn is_done(&self) -> bool {
+        return self.tick >= self.script.len();
+    }
+}
+
+impl Driver for Scripted {
+    fn controls(&mut self, _state: &GameState) -> Controls {
+        let controls = self.script.get(self.tick).copied().unwrap_or_default();
+        self.tick += 1;
+        return controls;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "line {}: {}", self.line, self.message);
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+//holds a random direction for a random while, like a cat on the keyboard
+pub struct RandomWalk<R: Rng> {
+    rng: R,
+    held: Controls,
+    remaining: u32,
+}
+
+impl<R: Rng> RandomWalk<R> {
+    pub fn new(rng: R) -> Self {
+        return RandomWalk {
+            rng,
+            held: Controls::default(),
+            remaining: 0,
+        };
+    }
+}
+
+impl<R: Rng> Driver for RandomWalk<R> {
+    fn controls(&mut self, _state: &GameState) -> Controls {
+        if self.remaining == 0 {
+            self.held = Controls::default();
+            match self.rng.gen_range(0..4) {
+                0 => self.held.up = true,
+                1 => self.held.down = true,
+                2 => self.held.left = true,
+                _ => self.held.right = true,
+            }
+            self.remaining = self.rng.gen_range(10..120);
+        }
+        self.remaining -= 1;
+        return self.held;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    pub completed: bool,
+    pub ticks: u64,
+    pub key_tick: Option<u64>, //when the key was picked up
+    pub rooms_visited: usize,
+    pub door_transitions: usize,
+    pub catches: usize, //times a ghost caught the player
+}
+
+impl Report {
+    pub fn seconds(&self) -> f64 {
+        return self.ticks as f64 * DT;
+    }
+}
+
+// Steps the game at DT with no window until the chest opens, a ghost or the
+// time limit ends the run, or we run out of ticks. Nothing here touches frenderer, so it runs anywhere `cargo test` does.
+pub fn run<D: Driver, R: Rng>(
+    state: &mut GameState,
+    driver: &mut D,
+    max_ticks: u64,
+    rng: &mut R,
+) -> Report {
+    let mut report = Report {
+        completed: false,
+        ticks: 0,
+        key_tick: None,
+        rooms_visited: 1,
+        door_transitions: 0,
+        catches: 0,
+    };
+    while report.ticks < max_ticks && !report.completed && !state.caught && !state.out_of_time {
+        let controls = driver.controls(state);
+        for event in rules::update(state, controls, rng) {
+            match event {
+                Event::DoorTraversed { .. } => report.door_transitions += 1,
+                Event::KeyCollected => report.key_tick = Some(report.ticks),
+                Event::ChestOpened => report.completed = true,
+                Event::Caught => report.catches += 1,
+                _ => {}
+            }
+        }
+        report.ticks += 1;
+    }
+    report.rooms_visited = state.rooms_visited();
+    return report;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn scripts_parse_counts_and_comments() {
+        let script = Scripted::parse("# warm up\n2 -\n3 WD # diagonal\n\n1 p\n").unwrap();
+        assert_eq!(script.script.len(), 6);
+        assert_eq!(script.script[2], Controls::from_keys("WD").unwrap());
+        assert!(script.script[5].play);
+    }
+
+    #[test]
+    fn written_scripts_parse_back() {
+        let frames = Scripted::parse("3 W\n1 -\n2 SD\n").unwrap().script;
+        assert_eq!(Scripted::write(&frames), "3 W\n1 -\n2 SD\n");
+        assert_eq!(
+            Scripted::parse(&Scripted::write(&frames)).unwrap().script,
+            frames
+        );
+    }
+
+    #[test]
+    fn script_errors_name_the_line() {
+        let err = Scripted::parse("1 W\nten W\n").err().unwrap();
+        assert_eq!(err.line, 2);
+        let err = Scripted::parse("1 W\n2 Q\n").err().unwrap();
+        assert_eq!(err.to_string(), "line 2: unknown key");
+    }
+
+    #[test]
+    fn scripted_run_through_a_two_room_house() {
+        let mut state = two_rooms();
+        //around the chest, through the north door, over the key, then
+        //back through the south door and into the chest
+        let mut driver = Scripted::parse("40 S\n40 A\n70 S\n110 W\n").unwrap();
+        let report = run(&mut state, &mut driver, 1000, &mut StdRng::seed_from_u64(0));
+        assert!(report.completed);
+        assert_eq!(report.door_transitions, 2);
+        assert_eq!(report.rooms_visited, 2);
+        assert!(report.key_tick.unwrap() < report.ticks);
+    }
+
+    #[test]
+    fn runs_stop_at_the_tick_limit() {
+        let mut state = two_rooms();
+        let mut driver = Scripted::new(vec![]);
+        let report = run(&mut state, &mut driver, 50, &mut StdRng::seed_from_u64(0));
+        assert!(!report.completed);
+        assert_eq!(report.ticks, 50);
+        assert_eq!(report.seconds(), 50.0 * DT);
+    }
+
+    #[test]
+    fn random_walk_is_repeatable() {
+        let play = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut state = GameState::generate(5, 2, 8, &mut rng);
+            let mut driver = RandomWalk::new(StdRng::seed_from_u64(seed));
+            return run(&mut state, &mut driver, 5000, &mut rng);
+        };
+        assert_eq!(play(9), play(9));
+    }
+}
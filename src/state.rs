@@ -0,0 +1,322 @@
+use crate::config::{GhostRules, House, Levels, Tuning};
+use crate::ghost::{self, Ghost};
+use crate::map::*;
+use crate::types::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use ultraviolet::Vec3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum GameplayState {
+    Mainscreen,
+    Instructions,
+    Play,
+    TimeAttack, //play against the clock, which starts at the first step
+    FinalScreen,
+}
+
+impl GameplayState {
+    //walking about a house, with or without the clock
+    pub fn is_playing(&self) -> bool {
+        return matches!(self, GameplayState::Play | GameplayState::TimeAttack);
+    }
+}
+
+//where the player stands in the first room at the start, and after a ghost catches them
+pub const START_POS: Vec3 = Vec3::new(20.0, 0.0, 0.0);
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Player {
+    pub pos: Vec3,
+    pub dir: Direction, //which way the character faces
+}
+
+// Pure game data: everything needed to simulate a run and nothing that needs a
+// Vulkan device. Models, cameras and sounds live on the front end's `World`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameState {
+    pub current_room: usize, //index of room in rooms
+    pub max_rooms: usize,    //rooms on the path, not counting dead ends
+    pub key_index: usize,
+    pub rooms: Vec<Room>,
+    pub doors: Vec<Door>,
+    pub is_finished: bool,
+    pub has_key: bool,
+    pub gameplaystate: GameplayState,
+    pub player: Player,
+    pub num_tex: usize,    //how many themes rooms can pick from
+    pub dead_ends: usize,  //rooms off the path; restarts have difficulty of them
+    pub floors: usize,     //storeys each house generated from this one is spread over
+    pub difficulty: usize, //extra rooms on the path each time the house restarts
+    pub generator: Generator,
+    pub tuning: Tuning,
+    pub visited: Vec<bool>, //rooms walked into this run, by index
+    pub ghost_rules: GhostRules,
+    pub ghosts: Vec<Ghost>,
+    pub cleared: usize, //houses whose chest was opened before this one
+    pub caught: bool,   //a ghost ended the run
+    //ticks on the time-attack clock, still 0 until the first step; None outside time attack
+    pub clock: Option<u64>,
+    pub levels: Option<Levels>, //the curves of the endless game; None in the classic game
+    pub level: usize,           //of the endless game, from 1
+    pub time_left: Option<u64>, //ticks before the house is lost; None without a time limit
+    pub out_of_time: bool,
+}
+
+impl GameState {
+    pub fn new(
+        rooms: Vec<Room>,
+        doors: Vec<Door>,
+        max_rooms: usize,
+        key_index: usize,
+        num_tex: usize,
+        gameplaystate: GameplayState,
+    ) -> Self {
+        let house = House::default();
+        let mut visited = vec![false; rooms.len()];
+        if let Some(first) = visited.first_mut() {
+            *first = true;
+        }
+        return GameState {
+            current_room: 0,
+            max_rooms,
+            key_index,
+            rooms,
+            doors,
+            is_finished: false,
+            has_key: false,
+            gameplaystate,
+            player: Player {
+                pos: START_POS,
+                dir: Direction::North,
+            },
+            num_tex,
+            dead_ends: house.dead_ends,
+            floors: house.floors,
+            difficulty: house.difficulty,
+            generator: house.generator,
+            tuning: Tuning::default(),
+            visited,
+            ghost_rules: GhostRules::default(),
+            ghosts: vec![],
+            cleared: 0,
+            caught: false,
+            clock: (gameplaystate == GameplayState::TimeAttack).then_some(0),
+            levels: None,
+            level: 1,
+            time_left: None,
+            out_of_time: false,
+        };
+    }
+
+    //a fresh house with the key somewhere along the path
+    pub fn generate<R: Rng>(
+        max_rooms: usize,
+        num_dead_ends: usize,
+        num_tex: usize,
+        rng: &mut R,
+    ) -> GameState {
+        return GameState::generate_with(
+            Generator::Path,
+            max_rooms,
+            num_dead_ends,
+            1,
+            num_tex,
+            rng,
+        );
+    }
+
+    pub fn generate_with<R: Rng>(
+        generator: Generator,
+        max_rooms: usize,
+        num_dead_ends: usize,
+        floors: usize,
+        num_tex: usize,
+        rng: &mut R,
+    ) -> GameState {
+        let (mut room_list, mut door_list) =
+            generator.generate(max_rooms as u32, num_dead_ends, num_tex, rng);
+        let keyidx = if max_rooms > 1 {
+            rng.gen_range(1..max_rooms)
+        } else {
+            0
+        };
+        stack_floors(&mut room_list, &mut door_list, floors, keyidx, rng);
+        let mut state = GameState::new(
+            room_list,
+            door_list,
+            max_rooms,
+            keyidx,
+            num_tex,
+            GameplayState::Play,
+        );
+        state.dead_ends = num_dead_ends;
+        state.floors = floors;
+        state.generator = generator;
+        return state;
+    }
+
+    //the house on a level of the endless game, loops, locks, ghosts, timer and all
+    #[allow(clippy::too_many_arguments)]
+    pub fn for_level<R: Rng>(
+        levels: Levels,
+        level: usize,
+        generator: Generator,
+        floors: usize,
+        num_tex: usize,
+        ghost_rules: GhostRules,
+        rng: &mut R,
+    ) -> GameState {
+        let stage = levels.stage(level);
+        let mut state = GameState::generate_with(
+            generator,
+            stage.rooms,
+            stage.dead_ends,
+            floors,
+            num_tex,
+            rng,
+        );
+        state.enter_level(levels, level, ghost_rules, rng);
+        return state;
+    }
+
+    // Makes a freshly built house the given level of the endless game: adds
+    // its loops and locks, sets its timer and lets its ghosts loose. Ghosts go
+    // as fast as if every level before had been cleared.
+    pub fn enter_level<R: Rng>(
+        &mut self,
+        levels: Levels,
+        level: usize,
+        ghost_rules: GhostRules,
+        rng: &mut R,
+    ) {
+        let stage = levels.stage(level);
+        add_loops(&mut self.rooms, &mut self.doors, stage.loops, rng);
+        lock_doors(
+            &self.rooms,
+            &mut self.doors,
+            stage.locked,
+            self.key_index,
+            rng,
+        );
+        self.levels = Some(levels);
+        self.level = level;
+        self.cleared = level - 1;
+        self.time_left = stage.time_limit;
+        let rules = GhostRules {
+            count: stage.ghosts,
+            ..ghost_rules
+        };
+        self.haunt(rules, rng);
+    }
+
+    // A bigger house with the key somewhere new; the player stays where they
+    // are. A run a ghost or the timer ended gets a new house of the same size
+    // instead, with the player back at the start. In the endless game that is
+    // the next level, or the same one again. A time attack goes on against a
+    // fresh clock.
+    pub fn restart<R: Rng>(&self, rng: &mut R) -> GameState {
+        let failed = self.caught || self.out_of_time;
+        let mut state = match self.levels {
+            Some(levels) => GameState::for_level(
+                levels,
+                self.level + !failed as usize,
+                self.generator,
+                self.floors,
+                self.num_tex,
+                self.ghost_rules,
+                rng,
+            ),
+            None => {
+                //as it always has, every house after the first has difficulty dead ends
+                let grow = if failed { 0 } else { self.difficulty };
+                let mut state = GameState::generate_with(
+                    self.generator,
+                    self.max_rooms + grow,
+                    self.difficulty,
+                    self.floors,
+                    self.num_tex,
+                    rng,
+                );
+                state.cleared = self.cleared + !failed as usize;
+                state.haunt(self.ghost_rules, rng);
+                state
+            }
+        };
+        state.difficulty = self.difficulty;
+        state.tuning = self.tuning;
+        if !failed {
+            state.player = self.player;
+        }
+        if self.clock.is_some() {
+            state.gameplaystate = GameplayState::TimeAttack;
+            state.clock = Some(0);
+        }
+        return state;
+    }
+
+    //sets the ghost rules and lets that many ghosts loose; none rolls nothing
+    pub fn haunt<R: Rng>(&mut self, rules: GhostRules, rng: &mut R) {
+        self.ghost_rules = rules;
+        self.ghosts = ghost::spawn(self, rules.count, rng);
+    }
+
+    pub fn rooms_visited(&self) -> usize {
+        return self.visited.iter().filter(|seen| **seen).count();
+    }
+
+    // Whether everything behind a door has been seen and there is nothing
+    // there left to do: no unvisited room, no key still lying about and no
+    // way round to the chest. What is behind a door is everything reachable
+    // without walking back through it, so a door onto a loop never counts.
+    pub fn explored_dead_end(&self, dooridx: usize) -> bool {
+        let door = self.doors[dooridx];
+        let from = self
+            .rooms
+            .iter()
+            .position(|room| room.doors.contains(&dooridx))
+            .unwrap();
+        let mut seen = vec![false; self.rooms.len()];
+        seen[from] = true;
+        let mut stack = vec![door.target];
+        while let Some(room) = stack.pop() {
+            if seen[room] {
+                continue;
+            }
+            seen[room] = true;
+            let key_left = room == self.key_index && !self.has_key;
+            if !self.visited[room] || key_left || room == 0 {
+                return false;
+            }
+            stack.extend(self.room_doors(room).map(|(_, next)| next.target));
+        }
+        return true;
+    }
+
+    //the door back out of door's room to from, or one in the middle of the wall opposite
+    pub fn door_back(&self, from: usize, door: Door) -> Door {
+        return self
+            .room_doors(door.target)
+            .map(|(_, back)| back)
+            .find(|back| back.target == from)
+            .unwrap_or_else(|| Door::new(door.spawn_pos, from, door.direction));
+    }
+
+    pub fn room_doors(&self, room: usize) -> impl Iterator<Item = (usize, Door)> + '_ {
+        return self.rooms[room]
+            .doors
+            .iter()
+            .map(move |dooridx| (*dooridx, self.doors[*dooridx]));
+    }
+}
+
+//two rooms joined by one door each way, for tests that only need somewhere to walk
+#[cfg(test)]
+pub(crate) fn two_rooms() -> GameState {
+    let rooms = vec![Room::new(vec![0], 0), Room::new(vec![1], 0)];
+    let doors = vec![
+        Door::new(Direction::North, 1, Direction::South),
+        Door::new(Direction::South, 0, Direction::North),
+    ];
+    return GameState::new(rooms, doors, 2, 1, 1, GameplayState::Play);
+}
@@ -0,0 +1,277 @@
+use crate::daily::History;
+use crate::identity;
+use crate::progress::Progress;
+use crate::state::GameState;
+use crate::times::Split;
+use crate::DT;
+use chrono::NaiveDate;
+
+// Text the front end draws with the bitmap font: where each glyph goes, and
+// what the HUD and the final screen say. Positions are in glyph cells from
+// the top left of the block, so the renderer only has to scale them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Placed {
+    pub ch: char,
+    pub col: usize,
+    pub row: usize,
+}
+
+//spaces take a cell but draw nothing
+pub fn layout(lines: &[String]) -> Vec<Placed> {
+    let mut placed = vec![];
+    for (row, line) in lines.iter().enumerate() {
+        for (col, ch) in line.chars().enumerate() {
+            if !ch.is_whitespace() {
+                placed.push(Placed { ch, col, row });
+            }
+        }
+    }
+    return placed;
+}
+
+//columns and rows the lines take up
+pub fn extent(lines: &[String]) -> (usize, usize) {
+    let cols = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    return (cols, lines.len());
+}
+
+//ticks as m:ss.t
+pub fn clock(ticks: u64) -> String {
+    let tenths = (ticks as f64 * DT * 10.0) as u64;
+    return format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10);
+}
+
+//how far ahead of or behind a best a time is, as -m:ss.t or +m:ss.t
+pub fn split(ticks: u64, best: u64) -> String {
+    if ticks < best {
+        return format!("-{}", clock(best - ticks));
+    }
+    return format!("+{}", clock(ticks - best));
+}
+
+//under the instructions, for the mode they don't mention
+pub fn instructions() -> Vec<String> {
+    return vec!["OR PRESS T TO PLAY AGAINST THE CLOCK".to_string()];
+}
+
+//the corner of the screen during play
+pub fn hud(state: &GameState, seed: u64, ticks: u64) -> Vec<String> {
+    let mut lines = vec![
+        format!("TIME {}", clock(ticks)),
+        format!("ROOMS {}/{}", state.rooms_visited(), state.rooms.len()),
+        if state.has_key {
+            "BRING THE KEY HOME".to_string()
+        } else {
+            "FIND THE KEY".to_string()
+        },
+        format!("SEED {}", seed),
+    ];
+    if state.floors > 1 {
+        let storey = state.rooms[state.current_room].storey;
+        lines.push(format!("FLOOR {}", storey + 1));
+    }
+    if state.levels.is_some() {
+        lines.push(format!("LEVEL {}", state.level));
+    }
+    if let Some(left) = state.time_left {
+        lines.push(format!("TIME LEFT {}", clock(left)));
+    }
+    return lines;
+}
+
+//across the top of the screen for a moment after walking into a room
+pub fn room_name(state: &GameState) -> Vec<String> {
+    return vec![identity::name(&state.rooms[state.current_room]).to_uppercase()];
+}
+
+//under the picture on the final screen
+pub fn final_screen(state: &GameState, seed: u64, ticks: u64) -> Vec<String> {
+    return vec![
+        if state.caught {
+            format!("CAUGHT AFTER {}", clock(ticks))
+        } else if state.out_of_time {
+            format!("OUT OF TIME AFTER {}", clock(ticks))
+        } else {
+            format!("OPENED IN {}", clock(ticks))
+        },
+        format!(
+            "{} OF {} ROOMS SEEN",
+            state.rooms_visited(),
+            state.rooms.len()
+        ),
+        format!("SEED {}", seed),
+    ];
+}
+
+//after the final screen's lines when the house was against the clock
+pub fn splits(times: &Split) -> Vec<String> {
+    let against = |best: Option<u64>, name: String| match best {
+        Some(best) => format!("{} BEST {} {}", name, clock(best), split(times.ticks, best)),
+        None => format!("FIRST {} TIME", name),
+    };
+    return vec![
+        against(times.seed_best, "SEED".to_string()),
+        against(times.rooms_best, format!("{} ROOM", times.rooms)),
+    ];
+}
+
+//after the final screen's lines in the endless game, with progress already recorded
+pub fn progress_lines(state: &GameState, progress: &Progress) -> Vec<String> {
+    let failed = state.caught || state.out_of_time;
+    return vec![
+        if failed {
+            format!("LEVEL {} AGAIN", state.level)
+        } else {
+            format!("LEVEL {} CLEARED", state.level)
+        },
+        format!("BEST LEVEL {}", progress.best),
+    ];
+}
+
+//earlier days the daily summary shows, latest first
+const DAYS_SHOWN: usize = 3;
+
+// After the final screen's lines for a daily house: how today's attempt, the
+// last in the history, went against today's others and against earlier days.
+pub fn daily_summary(history: &History, date: NaiveDate, ticks: u64) -> Vec<String> {
+    let mut lines = vec![format!("DAILY HOUSE {}", date)];
+    let tries = history.tries_on(date);
+    if tries > 1 {
+        let best = history.best_on(date).unwrap_or(ticks);
+        lines.push(format!("TRY {}, BEST TODAY {}", tries, clock(best)));
+    }
+    let before = history.days_before(date);
+    if before.is_empty() {
+        lines.push("FIRST DAILY HOUSE".to_string());
+    }
+    for day in before.iter().take(DAYS_SHOWN) {
+        lines.push(format!(
+            "{} {} {}",
+            day.date,
+            clock(day.ticks),
+            split(ticks, day.ticks)
+        ));
+    }
+    return lines;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clocks_count_minutes_seconds_and_tenths() {
+        assert_eq!(clock(0), "0:00.0");
+        assert_eq!(clock(90), "0:01.5");
+        assert_eq!(clock(60 * 75), "1:15.0");
+    }
+
+    #[test]
+    fn layout_skips_spaces_but_keeps_their_cells() {
+        let lines = vec!["A B".to_string(), "CD".to_string()];
+        let placed = layout(&lines);
+        let cells: Vec<(char, usize, usize)> =
+            placed.iter().map(|p| (p.ch, p.col, p.row)).collect();
+        assert_eq!(cells, [('A', 0, 0), ('B', 2, 0), ('C', 0, 1), ('D', 1, 1)]);
+        assert_eq!(extent(&lines), (3, 2));
+    }
+
+    #[test]
+    fn the_hud_follows_the_key() {
+        let mut state = GameState::generate(3, 1, 4, &mut rand::thread_rng());
+        let before = hud(&state, 7, 60);
+        assert_eq!(before[0], "TIME 0:01.0");
+        assert_eq!(before[1], "ROOMS 1/4");
+        state.has_key = true;
+        assert_ne!(hud(&state, 7, 60)[2], before[2]);
+    }
+
+    #[test]
+    fn the_floor_shows_only_in_houses_with_stairs() {
+        let mut state = GameState::generate(3, 1, 4, &mut rand::thread_rng());
+        assert_eq!(hud(&state, 7, 60).len(), 4);
+        state.floors = 2;
+        state.rooms[0].storey = 1;
+        assert_eq!(hud(&state, 7, 60)[4], "FLOOR 2");
+    }
+
+    #[test]
+    fn the_final_screen_says_how_the_run_ended() {
+        let mut state = GameState::generate(3, 1, 4, &mut rand::thread_rng());
+        assert_eq!(final_screen(&state, 7, 60)[0], "OPENED IN 0:01.0");
+        state.caught = true;
+        assert_eq!(final_screen(&state, 7, 60)[0], "CAUGHT AFTER 0:01.0");
+        state.caught = false;
+        state.out_of_time = true;
+        assert_eq!(final_screen(&state, 7, 60)[0], "OUT OF TIME AFTER 0:01.0");
+    }
+
+    #[test]
+    fn endless_levels_show_their_number_and_timer() {
+        let mut state = GameState::generate(3, 1, 4, &mut rand::thread_rng());
+        state.levels = Some(Default::default());
+        state.level = 5;
+        state.time_left = Some(60 * 30);
+        assert_eq!(hud(&state, 7, 60)[4..], ["LEVEL 5", "TIME LEFT 0:30.0"]);
+        let progress = Progress { level: 6, best: 9 };
+        assert_eq!(
+            progress_lines(&state, &progress),
+            ["LEVEL 5 CLEARED", "BEST LEVEL 9"]
+        );
+        state.out_of_time = true;
+        assert_eq!(progress_lines(&state, &progress)[0], "LEVEL 5 AGAIN");
+    }
+
+    #[test]
+    fn splits_say_how_far_off_the_best_a_time_is() {
+        assert_eq!(split(60, 90), "-0:00.5");
+        assert_eq!(split(90, 90), "+0:00.0");
+        let times = Split {
+            ticks: 600,
+            rooms: 6,
+            seed_best: Some(660),
+            rooms_best: None,
+        };
+        assert_eq!(
+            splits(&times),
+            ["SEED BEST 0:11.0 -0:01.0", "FIRST 6 ROOM TIME"]
+        );
+    }
+
+    #[test]
+    fn the_daily_summary_holds_today_up_against_earlier_days() {
+        let day = |d| NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+        let mut history = History::default();
+        history.record(day(19), 600);
+        assert_eq!(
+            daily_summary(&history, day(19), 600),
+            ["DAILY HOUSE 2026-10-19", "FIRST DAILY HOUSE"]
+        );
+        for (d, ticks) in [(12, 660), (15, 540), (16, 900), (18, 720)] {
+            history.record(day(d), ticks);
+        }
+        history.record(day(19), 630);
+        assert_eq!(
+            daily_summary(&history, day(19), 630),
+            [
+                "DAILY HOUSE 2026-10-19",
+                "TRY 2, BEST TODAY 0:10.0",
+                "2026-10-18 0:12.0 -0:01.5",
+                "2026-10-16 0:15.0 -0:04.5",
+                "2026-10-15 0:09.0 +0:01.5",
+            ]
+        );
+    }
+
+    #[test]
+    fn room_names_are_shouted() {
+        let mut state = GameState::generate(3, 1, 4, &mut rand::thread_rng());
+        state.rooms[0].floor_colour = 0;
+        state.rooms[0].landmark = 0;
+        assert_eq!(room_name(&state), ["BLUE STUDY"]);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::save::SaveFile;
+
+// Best time-attack times, kept in a local file between sessions. A house is
+// known by the seed of the session and how many rooms are on its path, and
+// the best for a room count is the best over every seed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BestTimes {
+    times: Vec<Best>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Best {
+    pub seed: u64,
+    pub rooms: usize,
+    pub ticks: u64,
+}
+
+// A finished house against the bests from before it, for the final screen.
+// None means there was nothing to beat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Split {
+    pub ticks: u64,
+    pub rooms: usize,
+    pub seed_best: Option<u64>,
+    pub rooms_best: Option<u64>,
+}
+
+impl BestTimes {
+    pub fn for_seed(&self, seed: u64, rooms: usize) -> Option<u64> {
+        return self
+            .times
+            .iter()
+            .find(|best| best.seed == seed && best.rooms == rooms)
+            .map(|best| best.ticks);
+    }
+
+    pub fn for_rooms(&self, rooms: usize) -> Option<u64> {
+        return self
+            .times
+            .iter()
+            .filter(|best| best.rooms == rooms)
+            .map(|best| best.ticks)
+            .min();
+    }
+
+    //a finished house against the bests so far, which it then joins if it beat them
+    pub fn record(&mut self, seed: u64, rooms: usize, ticks: u64) -> Split {
+        let split = Split {
+            ticks,
+            rooms,
+            seed_best: self.for_seed(seed, rooms),
+            rooms_best: self.for_rooms(rooms),
+        };
+        match self
+            .times
+            .iter_mut()
+            .find(|best| best.seed == seed && best.rooms == rooms)
+        {
+            Some(best) => best.ticks = best.ticks.min(ticks),
+            None => self.times.push(Best { seed, rooms, ticks }),
+        }
+        return split;
+    }
+}
+
+impl SaveFile for BestTimes {
+    const HEADER: &'static str = "scene3d best times: seed, rooms, ticks";
+    const KIND: &'static str = "a best times file";
+
+    fn records(&self) -> Vec<String> {
+        return self
+            .times
+            .iter()
+            .map(|best| format!("{} {} {}", best.seed, best.rooms, best.ticks))
+            .collect();
+    }
+
+    fn read(&mut self, words: &[&str]) -> Option<()> {
+        if words.len() != 3 {
+            return None;
+        }
+        self.times.push(Best {
+            seed: words[0].parse().ok()?,
+            rooms: words[1].parse().ok()?,
+            ticks: words[2].parse().ok()?,
+        });
+        return Some(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bests_are_kept_per_seed_and_per_room_count() {
+        let mut times = BestTimes::default();
+        let first = times.record(7, 4, 900);
+        assert_eq!((first.seed_best, first.rooms_best), (None, None));
+        times.record(8, 4, 1200);
+        times.record(7, 6, 500);
+        assert_eq!(times.for_seed(7, 4), Some(900));
+        assert_eq!(times.for_seed(8, 4), Some(1200));
+        assert_eq!(times.for_rooms(4), Some(900));
+        assert_eq!(times.for_rooms(5), None);
+
+        //a slower run splits against both and changes neither
+        let slower = times.record(8, 4, 1300);
+        assert_eq!(
+            (slower.seed_best, slower.rooms_best),
+            (Some(1200), Some(900))
+        );
+        assert_eq!(times.for_seed(8, 4), Some(1200));
+        let faster = times.record(8, 4, 800);
+        assert_eq!(
+            (faster.seed_best, faster.rooms_best),
+            (Some(1200), Some(900))
+        );
+        assert_eq!(
+            (times.for_seed(8, 4), times.for_rooms(4)),
+            (Some(800), Some(800))
+        );
+    }
+
+    #[test]
+    fn best_times_survive_the_round_trip_to_text() {
+        let mut times = BestTimes::default();
+        times.record(7, 4, 900);
+        times.record(u64::MAX, 12, 61234);
+        assert_eq!(BestTimes::parse(&times.to_text()).unwrap(), times);
+        assert!(BestTimes::parse("7 4\n").is_err());
+        assert!(BestTimes::parse("7 4 soon\n").is_err());
+    }
+}
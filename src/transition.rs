@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+
+// How one screen gives way to the next. The renderer can't blend two pictures,
+// so the front end draws the fade and wipe as a curtain of black tiles in front
+// of the camera and swaps what is behind it halfway through. A cross-fade
+// blends in time instead: each frame shows one picture or the other, the new
+// one on more and more of them, and at sixty frames a second the eye mixes them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Style {
+    Cut,       //swap at once, like before
+    Fade,      //dissolve to black, swap, dissolve back
+    Wipe,      //black sweeps in from the left and off to the right
+    CrossFade, //the new screen shows through on a growing share of frames
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Transition {
+    pub style: Style,
+    pub duration: f64, //seconds
+    elapsed: f64,
+    frames: u32, //ticked so far, which a cross-fade scatters between the two screens
+}
+
+impl Transition {
+    pub fn new(style: Style, duration: f64) -> Self {
+        return Transition {
+            style,
+            duration,
+            elapsed: 0.0,
+            frames: 0,
+        };
+    }
+
+    pub fn tick(&mut self, dt: f64) {
+        self.elapsed += dt;
+        self.frames += 1;
+    }
+
+    pub fn is_done(&self) -> bool {
+        return self.style == Style::Cut || self.elapsed >= self.duration;
+    }
+
+    //0 at the start, 1 at the end
+    pub fn progress(&self) -> f32 {
+        if self.is_done() || self.duration <= 0.0 {
+            return 1.0;
+        }
+        return (self.elapsed / self.duration) as f32;
+    }
+
+    //the old screen is drawn until halfway, the new one after; a cross-fade picks
+    //the new one on a frame when that frame's place in a scattered order is
+    //behind how far along it is
+    pub fn shows_new(&self) -> bool {
+        if self.style == Style::CrossFade && !self.is_done() {
+            return scattered(self.frames) < self.progress();
+        }
+        return self.progress() >= 0.5;
+    }
+
+    //how much of the screen is hidden, peaking halfway
+    pub fn cover(&self) -> f32 {
+        let peak = 1.0 - (2.0 * self.progress() - 1.0).abs();
+        return match self.style {
+            Style::Cut | Style::CrossFade => 0.0,
+            Style::Fade | Style::Wipe => peak,
+        };
+    }
+
+    //which tiles of a cols x rows curtain are black, row by row
+    pub fn curtain(&self, cols: usize, rows: usize) -> Vec<bool> {
+        let tiles = cols * rows;
+        let cover = self.cover();
+        return match self.style {
+            Style::Cut | Style::CrossFade => vec![false; tiles],
+            Style::Wipe => {
+                let edge = (cover * cols as f32).round() as usize;
+                (0..tiles)
+                    .map(|tile| {
+                        let col = tile % cols;
+                        if self.shows_new() {
+                            col >= cols - edge
+                        } else {
+                            col < edge
+                        }
+                    })
+                    .collect()
+            }
+            Style::Fade => {
+                let count = (cover * tiles as f32).round() as usize;
+                let order = dissolve_order(tiles);
+                let mut covered = vec![false; tiles];
+                for tile in order.into_iter().take(count) {
+                    covered[tile] = true;
+                }
+                covered
+            }
+        };
+    }
+}
+
+//the same scattered order every time; multiplying by an odd number is a
+//shuffle of u32, so no two tiles tie
+fn dissolve_order(tiles: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..tiles).collect();
+    order.sort_by_key(|tile| (*tile as u32).wrapping_mul(2654435761));
+    return order;
+}
+
+//frame n lands at the fractional part of n times the golden ratio, which never
+//bunches up, so any run of frames splits between the screens in the right share
+fn scattered(frame: u32) -> f32 {
+    return (frame as f64 * 0.618_033_988_749_895).fract() as f32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(style: Style, progress: f64) -> Transition {
+        let mut transition = Transition::new(style, 1.0);
+        transition.tick(progress);
+        return transition;
+    }
+
+    #[test]
+    fn cuts_are_over_at_once() {
+        let cut = Transition::new(Style::Cut, 1.0);
+        assert!(cut.is_done());
+        assert!(cut.shows_new());
+        assert!(cut.curtain(4, 3).iter().all(|tile| !tile));
+    }
+
+    #[test]
+    fn fades_and_wipes_cover_everything_at_the_swap() {
+        for style in [Style::Fade, Style::Wipe] {
+            assert!(!at(style, 0.4).shows_new());
+            assert!(at(style, 0.5).curtain(8, 6).iter().all(|tile| *tile));
+            assert!(at(style, 0.0).curtain(8, 6).iter().all(|tile| !tile));
+            assert!(at(style, 1.0).curtain(8, 6).iter().all(|tile| !tile));
+            assert!(at(style, 1.0).is_done());
+        }
+    }
+
+    #[test]
+    fn a_dissolve_only_adds_tiles_on_the_way_in() {
+        let early = at(Style::Fade, 0.1).curtain(8, 6);
+        let later = at(Style::Fade, 0.3).curtain(8, 6);
+        assert!(early.iter().zip(later.iter()).all(|(e, l)| !e || *l));
+        assert!(later.iter().filter(|tile| **tile).count() > 0);
+    }
+
+    #[test]
+    fn a_wipe_leaves_from_the_far_side() {
+        let leaving = at(Style::Wipe, 0.75).curtain(4, 1);
+        assert_eq!(leaving, vec![false, false, true, true]);
+        let arriving = at(Style::Wipe, 0.25).curtain(4, 1);
+        assert_eq!(arriving, vec![true, true, false, false]);
+    }
+
+    #[test]
+    fn cross_fades_never_go_black() {
+        let mut fade = Transition::new(Style::CrossFade, 1.0);
+        let mut shown_new = vec![];
+        while !fade.is_done() {
+            assert!(fade.curtain(8, 6).iter().all(|tile| !tile));
+            shown_new.push(fade.shows_new());
+            fade.tick(1.0 / 60.0);
+        }
+        assert!(fade.shows_new());
+        //mostly the old screen at first, an even mix halfway, mostly the new one last
+        let share = |frames: &[bool]| {
+            frames.iter().filter(|new| **new).count() as f32 / frames.len() as f32
+        };
+        assert!(share(&shown_new[0..12]) < 0.2);
+        assert!((share(&shown_new[24..36]) - 0.5).abs() < 0.15);
+        assert!(share(&shown_new[48..60]) > 0.8);
+    }
+}
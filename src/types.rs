@@ -1,5 +1,7 @@
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Vec2i {
     pub x: i32,
     pub y: i32,
@@ -15,14 +17,14 @@ impl std::ops::Add<Vec2i> for Vec2i {
         }
     }
 }
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum GameObject {
     Key,
     Chair,
     LockedChest,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Direction {
     North,
     East,
@@ -30,10 +32,51 @@ pub enum Direction {
     West,
 }
 
-#[derive(Clone, Debug)]
+//which way a staircase goes from the room it starts in
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Stairs {
+    Up,
+    Down,
+}
+
+//shapes are measured in steps; a wall of a square room is this many steps long
+pub const STEPS: i32 = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Corner {
+    NorthEast,
+    SouthEast,
+    SouthWest,
+    NorthWest,
+}
+
+// The floor plan of a room, centred in its grid cell: a box of width by depth
+// steps, with one corner cut away up to the middle for an L. Corridors are
+// just narrow boxes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Shape {
+    pub width: i32, //along x, even so the middle falls on a step
+    pub depth: i32, //along z
+    pub notch: Option<Corner>,
+}
+
+impl Shape {
+    pub const SQUARE: Shape = Shape {
+        width: STEPS,
+        depth: STEPS,
+        notch: None,
+    };
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Room {
     pub doors: Vec<usize>,
-    pub tex_idx: usize,        //figure out the type for a texture
+    pub tex_idx: usize,        //which theme the room is painted in
+    pub pos: Option<Vec2i>,    //grid cell, if the room fits the floor plan around room 0
+    pub floor_colour: usize,   //index into identity::FLOOR_COLOURS
+    pub landmark: usize,       //index into identity::LANDMARKS
+    pub shape: Shape,
+    pub storey: usize,         //floor of the house it is on, counting up from the chest's
     // pub objects: Vec<GameObject>, //vec of game objects, perhaps including a key
 }
 
@@ -42,19 +85,28 @@ impl Room {
         return Room {
             doors,
             tex_idx,
+            pos: None,
+            floor_colour: 0,
+            landmark: 0,
+            shape: Shape::SQUARE,
+            storey: 0,
             // objects,
         };
     }
 }
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Door {
     pub direction: Direction,
     pub target: usize, //where it goes, Room
     pub spawn_pos: Direction, //which door you come from
+    pub offset: i32, //steps along the wall from the middle of the room, +x or +z
+    pub uses: u32, //times walked through this run
+    pub stairs: Option<Stairs>, //a flight up or down to another storey instead of a level door
+    pub locked: bool, //shut until the key is found
 }
 impl Door {
     pub fn new(direction: Direction, target: usize, spawn_pos: Direction) -> Self {
-        return Door { direction, target , spawn_pos};
+        return Door { direction, target , spawn_pos, offset: 0, uses: 0, stairs: None, locked: false };
     }
     // pub fn new(direction: Direction, target: usize) -> Self {
     //     Door::new(direction, target);
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+//what a file looked like last time: None once it is gone
+type Stamp = Option<(SystemTime, u64)>;
+
+// Polls modification times and sizes instead of asking the OS for change
+// events, so it works the same everywhere. Cheap enough to call a couple of
+// times a second for the few dozen files the game loads.
+#[derive(Clone, Debug, Default)]
+pub struct Watcher {
+    files: BTreeMap<PathBuf, Stamp>,
+}
+
+impl Watcher {
+    pub fn new() -> Self {
+        return Watcher::default();
+    }
+
+    pub fn watch(&mut self, path: &Path) {
+        self.files.insert(path.to_path_buf(), stamp(path));
+    }
+
+    pub fn len(&self) -> usize {
+        return self.files.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.files.is_empty();
+    }
+
+    //files written, created or deleted since the last poll
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut changed = vec![];
+        for (path, last) in self.files.iter_mut() {
+            let now = stamp(path);
+            if now != *last {
+                *last = now;
+                changed.push(path.clone());
+            }
+        }
+        return changed;
+    }
+}
+
+fn stamp(path: &Path) -> Stamp {
+    let meta = std::fs::metadata(path).ok()?;
+    return Some((meta.modified().ok()?, meta.len()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_each_change_once() {
+        let dir = std::env::temp_dir().join(format!("scene3d-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("config.toml");
+        std::fs::write(&file, "a").unwrap();
+
+        let mut watcher = Watcher::new();
+        watcher.watch(&file);
+        watcher.watch(&dir.join("not yet.png"));
+        assert!(watcher.poll().is_empty());
+
+        //a different size is a change even on filesystems with coarse mtimes
+        std::fs::write(&file, "abc").unwrap();
+        assert_eq!(watcher.poll(), vec![file.clone()]);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::write(dir.join("not yet.png"), "").unwrap();
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(watcher.poll().len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+#![allow(clippy::needless_return)]
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use scene3d::geometry::*;
+use scene3d::map::*;
+use scene3d::rules::*;
+use scene3d::state::*;
+use scene3d::types::*;
+use std::collections::VecDeque;
+
+//door indices along the shortest way from one room to another
+fn path(state: &GameState, from: usize, to: usize) -> Vec<usize> {
+    let mut prev: Vec<Option<usize>> = vec![None; state.rooms.len()];
+    let mut seen = vec![false; state.rooms.len()];
+    let mut queue = VecDeque::from([from]);
+    seen[from] = true;
+    while let Some(room) = queue.pop_front() {
+        for (dooridx, door) in state.room_doors(room) {
+            if !seen[door.target] {
+                seen[door.target] = true;
+                prev[door.target] = Some(dooridx);
+                queue.push_back(door.target);
+            }
+        }
+    }
+    let mut doors = vec![];
+    let mut room = to;
+    while room != from {
+        let dooridx = prev[room].expect("room is unreachable");
+        doors.push(dooridx);
+        room = state
+            .rooms
+            .iter()
+            .position(|r| r.doors.contains(&dooridx))
+            .unwrap();
+    }
+    doors.reverse();
+    return doors;
+}
+
+//stand just short of a door and walk into it
+fn walk_through(state: &mut GameState, dooridx: usize, rng: &mut StdRng) -> Vec<Event> {
+    let door = state.doors[dooridx];
+    let tuning = state.tuning;
+    let shape = state.rooms[state.current_room].shape;
+    let wall = door_pos(shape, door, tuning.room_size);
+    let mut controls = Controls::default();
+    match door.direction {
+        Direction::North => controls.down = true,
+        Direction::South => controls.up = true,
+        Direction::East => controls.right = true,
+        Direction::West => controls.left = true,
+    }
+    state.player.pos = wall + get_inward(door.direction) * 2.0 * tuning.buffer;
+    let mut events = vec![];
+    for _ in 0..60 {
+        let tick = update(state, controls, rng);
+        let done = tick
+            .iter()
+            .any(|e| matches!(e, Event::DoorTraversed { .. }));
+        events.extend(tick);
+        if done {
+            break;
+        }
+    }
+    return events;
+}
+
+fn fresh_state(seed: u64) -> (GameState, StdRng) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (mut rooms, mut doors) = generate_room_map(6, 3, 8, &mut rng);
+    shape_rooms(&mut rooms, &mut doors, &mut rng);
+    let state = GameState::new(rooms, doors, 6, 4, 8, GameplayState::Play);
+    return (state, rng);
+}
+
+#[test]
+fn every_room_is_reachable_from_the_start() {
+    for seed in 0..20 {
+        let (state, _) = fresh_state(seed);
+        for room in 1..state.rooms.len() {
+            assert!(!path(&state, 0, room).is_empty());
+        }
+    }
+}
+
+#[test]
+fn fetch_the_key_and_open_the_chest() {
+    for seed in 0..20 {
+        let (mut state, mut rng) = fresh_state(seed);
+        let mut events = vec![];
+        for dooridx in path(&state, 0, state.key_index) {
+            events.extend(walk_through(&mut state, dooridx, &mut rng));
+        }
+        assert_eq!(state.current_room, state.key_index);
+
+        //the key floats in the middle of the room
+        state.player.pos = ultraviolet::Vec3::zero();
+        events.extend(update(&mut state, Controls::default(), &mut rng));
+        assert!(state.has_key);
+
+        for dooridx in path(&state, state.key_index, 0) {
+            events.extend(walk_through(&mut state, dooridx, &mut rng));
+        }
+        assert_eq!(state.current_room, 0);
+
+        let up = Controls {
+            up: true,
+            ..Controls::default()
+        };
+        state.player.pos = ultraviolet::Vec3::new(0.0, 0.0, 10.0);
+        for _ in 0..20 {
+            events.extend(update(&mut state, up, &mut rng));
+        }
+        assert!(state.is_finished);
+        assert_eq!(state.gameplaystate, GameplayState::FinalScreen);
+        assert_eq!(
+            events.iter().filter(|e| **e == Event::KeyCollected).count(),
+            1
+        );
+        assert_eq!(
+            events.iter().filter(|e| **e == Event::ChestOpened).count(),
+            1
+        );
+    }
+}
+
+#[test]
+fn houses_grow_after_every_win() {
+    let (mut state, mut rng) = fresh_state(7);
+    let restart = Controls {
+        restart: true,
+        ..Controls::default()
+    };
+    for level in 1..5 {
+        state.gameplaystate = GameplayState::FinalScreen;
+        update(&mut state, restart, &mut rng);
+        assert_eq!(state.max_rooms, 6 + level * state.difficulty);
+        assert_eq!(state.rooms.len(), state.max_rooms + state.difficulty);
+        assert_eq!(state.current_room, 0);
+    }
+}
+
+#[test]
+fn doors_into_explored_dead_ends_are_known() {
+    for seed in 0..20 {
+        let (mut state, mut rng) = fresh_state(seed);
+        //a room with one door that isn't the key room
+        let leaf = (1..state.rooms.len())
+            .find(|room| state.rooms[*room].doors.len() == 1 && *room != state.key_index)
+            .unwrap();
+        let there = path(&state, 0, leaf);
+        let last = *there.last().unwrap();
+        assert!(!state.explored_dead_end(last));
+        for dooridx in there.iter() {
+            walk_through(&mut state, *dooridx, &mut rng);
+        }
+        let back = state.rooms[leaf].doors[0];
+        walk_through(&mut state, back, &mut rng);
+        assert_eq!(state.doors[last].uses, 1);
+        assert_eq!(state.doors[back].uses, 1);
+        assert!(state.explored_dead_end(last), "seed {}", seed);
+        //the way home is never a dead end
+        assert!(!state.explored_dead_end(back));
+    }
+}